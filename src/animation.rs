@@ -0,0 +1,211 @@
+// sprite animations: clips of frames on a sprite sheet, defined in assets/animations.cfg,
+// and the animator each entity uses to play the clip that matches its state
+use std::collections::HashMap;
+
+use crate::assets::Assets;
+
+// a rect of the sheet in pixels, shown for duration seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub duration: f32,
+}
+
+pub struct Clip {
+    pub sheet: String,
+    pub frames: Vec<Frame>,
+    pub looping: bool,
+    // the way the art faces, the renderer mirrors it for entities facing the other way
+    pub faces_left: bool,
+}
+
+impl Clip {
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    // the frame shown time seconds in, looping clips wrap around and others hold their last frame
+    pub fn frame_at(&self, time: f32) -> Option<&Frame> {
+        let duration = self.duration();
+        let mut time = if self.looping && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time
+        };
+        for frame in &self.frames {
+            if time < frame.duration {
+                return Some(frame)
+            }
+            time -= frame.duration;
+        }
+        self.frames.last()
+    }
+}
+
+#[derive(Default)]
+pub struct Animations {
+    clips: HashMap<String, Clip>,
+}
+
+impl Animations {
+    pub const PATH: &'static str = "animations.cfg";
+
+    pub fn get(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+
+    // "clip <name> <sheet> <loop|once> <left|right>" starts a clip and each
+    // "frame <x> <y> <width> <height> <seconds>" after it adds a frame to it.
+    // blank lines and lines starting with # are skipped
+    pub fn from_config_str(config: &str) -> Result<Animations, String> {
+        let mut clips = HashMap::new();
+        let mut current: Option<(String, Clip)> = None;
+        let finish = |clips: &mut HashMap<String, Clip>, current: Option<(String, Clip)>| match current {
+            Some((name, clip)) if clip.frames.is_empty() => Err(format!("clip {} has no frames", name)),
+            Some((name, clip)) => {
+                clips.insert(name, clip);
+                Ok(())
+            },
+            None => Ok(()),
+        };
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["clip", name, sheet, mode, facing] => {
+                    let looping = match mode {
+                        "loop" => true,
+                        "once" => false,
+                        _ => return Err(error("expected loop or once")),
+                    };
+                    let faces_left = match facing {
+                        "left" => true,
+                        "right" => false,
+                        _ => return Err(error("expected left or right")),
+                    };
+                    finish(&mut clips, current.take())?;
+                    current = Some((name.to_string(), Clip { sheet: sheet.to_string(), frames: vec![], looping, faces_left }));
+                },
+                ["frame", x, y, width, height, duration] => {
+                    let (_, clip) = current.as_mut().ok_or_else(|| error("frame before any clip"))?;
+                    let pixels = |value: &str| value.parse::<u32>().map_err(|_| error(&format!("{} is not a pixel position", value)));
+                    let duration = duration.parse::<f32>().ok().filter(|duration| *duration > 0.0)
+                        .ok_or_else(|| error(&format!("{} is not a positive number of seconds", duration)))?;
+                    clip.frames.push(Frame { x: pixels(x)?, y: pixels(y)?, width: pixels(width)?, height: pixels(height)?, duration });
+                },
+                _ => return Err(error("expected clip <name> <sheet> <loop|once> <left|right> or frame <x> <y> <width> <height> <seconds>")),
+            }
+        }
+        finish(&mut clips, current)?;
+        Ok(Animations { clips })
+    }
+
+    // falls back to no clips, which draws every entity as its whole sheet
+    pub fn load(assets: &mut Assets) -> Animations {
+        let parsed = assets.load_data(Self::PATH)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|bytes| Animations::from_config_str(&String::from_utf8_lossy(&bytes)));
+        parsed.unwrap_or_else(|err| {
+            log::warn!("Couldn't load {}: {}", Self::PATH, err);
+            Animations::default()
+        })
+    }
+}
+
+// plays one clip at a time, entities tell it which clip their state calls for every update
+pub struct Animator {
+    clip: &'static str,
+    time: f32,
+}
+
+impl Animator {
+    pub fn new(clip: &'static str) -> Self {
+        Self { clip, time: 0.0 }
+    }
+
+    pub fn clip(&self) -> &'static str {
+        self.clip
+    }
+
+    // seconds since the clip started
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // switching to a different clip starts it from the beginning
+    pub fn update(&mut self, delta_time: f32, clip: &'static str) {
+        if clip != self.clip {
+            self.clip = clip;
+            self.time = 0.0;
+        } else {
+            self.time += delta_time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(looping: bool) -> Clip {
+        let frame = |x, duration| Frame { x, y: 0, width: 16, height: 16, duration };
+        Clip { sheet: "player.png".to_string(), frames: vec![frame(0, 0.1), frame(16, 0.2), frame(32, 0.1)], looping, faces_left: false }
+    }
+
+    #[test]
+    fn looping_clips_wrap_around() {
+        let clip = clip(true);
+        let x_at = |time| clip.frame_at(time).unwrap().x;
+        assert_eq!(x_at(0.0), 0);
+        assert_eq!(x_at(0.15), 16);
+        assert_eq!(x_at(0.35), 32);
+        assert_eq!(x_at(0.45), 0);
+        assert_eq!(x_at(0.55), 16);
+        assert_eq!(x_at(4.05), 0);
+    }
+
+    #[test]
+    fn once_clips_hold_the_last_frame() {
+        let clip = clip(false);
+        let x_at = |time| clip.frame_at(time).unwrap().x;
+        assert_eq!(x_at(0.0), 0);
+        assert_eq!(x_at(0.15), 16);
+        assert_eq!(x_at(0.35), 32);
+        assert_eq!(x_at(0.45), 32);
+        assert_eq!(x_at(100.0), 32);
+    }
+
+    #[test]
+    fn parses_clips() {
+        let config = "# player\n\nclip run player.png loop right\nframe 0 0 16 16 0.1\nframe 16 0 16 16 0.1\nclip die basic.png once left\nframe 0 32 16 8 0.5\n";
+        let animations = Animations::from_config_str(config).unwrap();
+        let run = animations.get("run").unwrap();
+        assert_eq!(run.sheet, "player.png");
+        assert!(run.looping && !run.faces_left);
+        assert_eq!(run.frames.len(), 2);
+        assert!((run.duration() - 0.2).abs() < 1e-6);
+        let die = animations.get("die").unwrap();
+        assert!(!die.looping && die.faces_left);
+        assert_eq!(die.frames, vec![Frame { x: 0, y: 32, width: 16, height: 8, duration: 0.5 }]);
+        assert!(animations.get("idle").is_none());
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let error = |config: &str| Animations::from_config_str(config).err().unwrap();
+        assert_eq!(error("frame 0 0 16 16 0.1"), "line 1: frame before any clip");
+        assert_eq!(error("clip run player.png forever right"), "line 1: expected loop or once");
+        assert_eq!(error("clip run player.png loop up"), "line 1: expected left or right");
+        assert_eq!(error("clip run player.png loop right\nframe -1 0 16 16 0.1"), "line 2: -1 is not a pixel position");
+        assert_eq!(error("clip run player.png loop right\nframe 0 0 16 16 0"), "line 2: 0 is not a positive number of seconds");
+        assert_eq!(error("clip run player.png loop right\nclip idle player.png loop right\nframe 0 0 16 16 0.1"), "clip run has no frames");
+        assert_eq!(error("clip run player.png loop right"), "clip run has no frames");
+        assert!(error("clip run player.png loop right\nframe 0 0 16").starts_with("line 2: expected clip"));
+    }
+}
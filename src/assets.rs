@@ -0,0 +1,371 @@
+// loads textures, fonts and sounds by their path under assets/, each only once, and hands out
+// typed handles to them. failures say which asset and why instead of panicking where they happen.
+// native debug builds read assets/ at runtime and reload files as they change.
+use std::{borrow::Cow, collections::HashMap, hash::{Hash, Hasher}, marker::PhantomData};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::graphics::{atlas::{self, Atlas}, texture::Texture, textured::UvRect, text::{Font, make_font_infos, default_characters}};
+
+pub const ASSET_DIR: &str = "assets";
+
+// release and web builds only use the copies built into the game
+pub const HOT_RELOAD: bool = cfg!(all(not(target_arch = "wasm32"), debug_assertions));
+
+// index of an asset of type T in the Assets it was loaded by
+pub struct Handle<T> {
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize) -> Self {
+        Self { index, marker: PhantomData }
+    }
+}
+
+// derives would require T to implement these too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.index)
+    }
+}
+
+// encoded audio, decoded when it is played
+pub struct Sound {
+    pub bytes: Vec<u8>,
+}
+
+// assets are built into the binary, since the web build has no file system
+// and release builds should run without the assets directory next to them
+macro_rules! embedded {
+    ($($path:literal),* $(,)?) => {
+        fn embedded(path: &str) -> Option<&'static [u8]> {
+            match path {
+                $($path => Some(include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/", $path))),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+embedded![
+    "arial.ttf",
+    "background.png",
+    "basic.png",
+    "player.png",
+    "spearman.png",
+    "tile_glass_holy.png",
+    "tile_stained_glass.png",
+    "red_ball.png",
+    "green_ball.png",
+    "Cursed_Church_Set_V2.mp3",
+    "animations.cfg",
+    "backgrounds.cfg",
+    "spells.cfg",
+    "level.txt",
+    "happy-tree.png",
+];
+
+// the bytes of an asset, preferring the file under assets/ when hot reloading
+pub fn read(path: &str) -> Result<Cow<'static, [u8]>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if HOT_RELOAD {
+        let file_path = std::path::Path::new(ASSET_DIR).join(path);
+        match std::fs::read(&file_path) {
+            Ok(bytes) => return Ok(Cow::Owned(bytes)),
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound =>
+                return Err(err).with_context(|| format!("couldn't read {}", file_path.display())),
+            Err(_) => (),
+        }
+    }
+    embedded(path).map(Cow::Borrowed).ok_or_else(|| anyhow!("{} is not in {}/ or built into the game", path, ASSET_DIR))
+}
+
+// what changed when a file was reloaded
+pub enum Reloaded {
+    // the new pixels were written into the existing texture
+    Texture,
+    // the texture or font with this id was remade, so bind groups made for it are stale
+    Replaced(String),
+    Sound(Handle<Sound>),
+    // a data file that whoever loaded it should parse again
+    Data(String),
+}
+
+pub struct Assets {
+    textures: Vec<Texture>,
+    // the path and filter each texture was loaded with, None for textures made in code
+    texture_sources: Vec<Option<(String, wgpu::FilterMode)>>,
+    texture_paths: HashMap<String, Handle<Texture>>,
+    fonts: Vec<Font>,
+    font_sources: Vec<(String, f32)>,
+    // fonts are rasterized at one size, so the same file at two sizes is two fonts
+    font_paths: HashMap<(String, u32), Handle<Font>>,
+    sounds: Vec<Sound>,
+    sound_paths: HashMap<String, Handle<Sound>>,
+    atlases: Vec<Atlas>,
+    data_paths: Vec<String>,
+    // a 1x1 white texture, drawn in place of textures that weren't loaded
+    pub blank: Handle<Texture>,
+}
+
+impl Assets {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Self> {
+        let blank = Texture::blank_texture(device, queue, "blank").context("couldn't create the blank texture")?;
+        let mut assets = Self {
+            textures: vec![],
+            texture_sources: vec![],
+            texture_paths: HashMap::new(),
+            fonts: vec![],
+            font_sources: vec![],
+            font_paths: HashMap::new(),
+            sounds: vec![],
+            sound_paths: HashMap::new(),
+            atlases: vec![],
+            data_paths: vec![],
+            blank: Handle::new(0),
+        };
+        assets.blank = assets.add_texture(blank);
+        Ok(assets)
+    }
+
+    // adds a texture made in code, which can't be looked up by path
+    pub fn add_texture(&mut self, texture: Texture) -> Handle<Texture> {
+        self.textures.push(texture);
+        self.texture_sources.push(None);
+        Handle::new(self.textures.len() - 1)
+    }
+
+    pub fn load_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, path: &str, filter: wgpu::FilterMode) -> Result<Handle<Texture>> {
+        if let Some(handle) = self.texture_paths.get(path) {
+            return Ok(*handle)
+        }
+        let texture = read(path)
+            .and_then(|bytes| Texture::from_image_bytes(device, queue, &bytes, path, filter))
+            .with_context(|| format!("couldn't load texture {}", path))?;
+        let handle = self.add_texture(texture);
+        self.texture_sources[handle.index] = Some((path.to_string(), filter));
+        self.texture_paths.insert(path.to_string(), handle);
+        Ok(handle)
+    }
+
+    // swaps in a texture made in code again, e.g. one that changed size. its old bind groups are stale
+    pub fn replace_texture(&mut self, handle: Handle<Texture>, texture: Texture) {
+        self.textures[handle.index] = texture;
+    }
+
+    pub fn texture(&self, handle: Handle<Texture>) -> &Texture {
+        &self.textures[handle.index]
+    }
+
+    pub fn textures(&self) -> impl Iterator<Item = &Texture> {
+        self.textures.iter()
+    }
+
+    pub fn load_font(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, path: &str, size: f32) -> Result<Handle<Font>> {
+        let key = (path.to_string(), size.to_bits());
+        if let Some(handle) = self.font_paths.get(&key) {
+            return Ok(*handle)
+        }
+        let font = read(path)
+            .and_then(|bytes| make_font(device, queue, &bytes, path, size))
+            .with_context(|| format!("couldn't load font {} at size {}", path, size))?;
+        self.fonts.push(font);
+        self.font_sources.push((path.to_string(), size));
+        let handle = Handle::new(self.fonts.len() - 1);
+        self.font_paths.insert(key, handle);
+        Ok(handle)
+    }
+
+    pub fn font(&self, handle: Handle<Font>) -> &Font {
+        &self.fonts[handle.index]
+    }
+
+    pub fn fonts(&self) -> impl Iterator<Item = &Font> {
+        self.fonts.iter()
+    }
+
+    pub fn load_sound(&mut self, path: &str) -> Result<Handle<Sound>> {
+        if let Some(handle) = self.sound_paths.get(path) {
+            return Ok(*handle)
+        }
+        let bytes = read(path).with_context(|| format!("couldn't load sound {}", path))?;
+        self.sounds.push(Sound { bytes: bytes.into_owned() });
+        let handle = Handle::new(self.sounds.len() - 1);
+        self.sound_paths.insert(path.to_string(), handle);
+        Ok(handle)
+    }
+
+    pub fn sound(&self, handle: Handle<Sound>) -> &Sound {
+        &self.sounds[handle.index]
+    }
+
+    // packs the images at paths into one texture named id
+    pub fn load_atlas(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: &str, paths: &[&str], filter: wgpu::FilterMode) -> Result<Handle<Atlas>> {
+        let paths: Vec<String> = paths.iter().map(|path| path.to_string()).collect();
+        let (image, regions) = pack_atlas(&paths).with_context(|| format!("couldn't build atlas {}", id))?;
+        let texture = Texture::from_image(device, queue, &image::DynamicImage::ImageRgba8(image.clone()), id, filter)?;
+        let texture = self.add_texture(texture);
+        self.atlases.push(Atlas::new(texture, filter, paths, &image, regions));
+        Ok(Handle::new(self.atlases.len() - 1))
+    }
+
+    pub fn atlas(&self, handle: Handle<Atlas>) -> &Atlas {
+        &self.atlases[handle.index]
+    }
+
+    // the bytes of a file the game parses itself, watched for changes like the other assets
+    pub fn load_data(&mut self, path: &str) -> Result<Cow<'static, [u8]>> {
+        if !self.data_paths.iter().any(|data_path| data_path == path) {
+            self.data_paths.push(path.to_string());
+        }
+        read(path)
+    }
+
+    // every file loaded so far
+    pub fn paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self.texture_sources.iter().flatten().map(|(path, _)| path.as_str())
+            .chain(self.font_sources.iter().map(|(path, _)| path.as_str()))
+            .chain(self.sound_paths.keys().map(String::as_str))
+            .chain(self.atlases.iter().flat_map(|atlas| atlas.paths()).map(String::as_str))
+            .chain(self.data_paths.iter().map(String::as_str))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    // loads path again into the slots of everything that was loaded from it, keeping their handles
+    pub fn reload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, path: &str) -> Result<Vec<Reloaded>> {
+        let bytes = read(path)?;
+        let mut reloaded = vec![];
+        if self.data_paths.iter().any(|data_path| data_path == path) {
+            reloaded.push(Reloaded::Data(path.to_string()));
+        }
+        if let Some(handle) = self.texture_paths.get(path).copied() {
+            let image = image::load_from_memory(&bytes).with_context(|| format!("couldn't decode {}", path))?;
+            let texture = &mut self.textures[handle.index];
+            // a texture that changed size can't be written in place
+            if texture.write_image(queue, &image).is_ok() {
+                reloaded.push(Reloaded::Texture);
+            } else {
+                let filter = self.texture_sources[handle.index].as_ref().map_or(wgpu::FilterMode::Nearest, |(_, filter)| *filter);
+                *texture = Texture::from_image(device, queue, &image, path, filter)?;
+                reloaded.push(Reloaded::Replaced(path.to_string()));
+            }
+        }
+        for i in 0..self.fonts.len() {
+            let (font_path, size) = &self.font_sources[i];
+            if font_path == path {
+                self.fonts[i] = make_font(device, queue, &bytes, path, *size)?;
+                reloaded.push(Reloaded::Replaced(path.to_string()));
+            }
+        }
+        if let Some(handle) = self.sound_paths.get(path).copied() {
+            self.sounds[handle.index].bytes = bytes.into_owned();
+            reloaded.push(Reloaded::Sound(handle));
+        }
+        for atlas in self.atlases.iter_mut().filter(|atlas| atlas.contains(path)) {
+            // the whole atlas is packed again, since the sprite may have changed size
+            let (image, regions) = pack_atlas(atlas.paths())?;
+            atlas.set_regions(&image, regions);
+            let image = image::DynamicImage::ImageRgba8(image);
+            let texture = &mut self.textures[atlas.texture.index];
+            if texture.write_image(queue, &image).is_ok() {
+                reloaded.push(Reloaded::Texture);
+            } else {
+                *texture = Texture::from_image(device, queue, &image, &texture.id, atlas.filter)?;
+                reloaded.push(Reloaded::Replaced(texture.id.clone()));
+            }
+        }
+        Ok(reloaded)
+    }
+}
+
+fn pack_atlas(paths: &[String]) -> Result<(image::RgbaImage, HashMap<String, UvRect>)> {
+    let images = paths.iter().map(|path| {
+        let bytes = read(path)?;
+        let image = image::load_from_memory(&bytes).with_context(|| format!("couldn't decode {}", path))?;
+        Ok((path.clone(), image.to_rgba8()))
+    }).collect::<Result<Vec<_>>>()?;
+    atlas::pack_images(&images)
+}
+
+fn make_font(device: &wgpu::Device, queue: &wgpu::Queue, bytes: &[u8], path: &str, size: f32) -> Result<Font> {
+    let infos = make_font_infos(bytes, &[size], default_characters().iter(), None, path.to_string())
+        .map_err(|err| anyhow!(err))?;
+    Font::make_from_info(device, queue, &infos[0], wgpu::FilterMode::Linear).map_err(|err| anyhow!(err))
+}
+
+// polls the files of loaded assets for changes, like TuningWatcher does for tuning.cfg
+pub struct AssetWatcher {
+    #[cfg(not(target_arch = "wasm32"))]
+    modified: HashMap<String, std::time::SystemTime>,
+    timer: f32,
+}
+
+impl Default for AssetWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetWatcher {
+    const POLL_INTERVAL: f32 = 0.5;
+
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            modified: HashMap::new(),
+            timer: 0.0,
+        }
+    }
+
+    // paths whose files changed since the last poll, a path seen for the first time only has its time recorded
+    pub fn poll(&mut self, delta_time: f32, assets: &Assets) -> Vec<String> {
+        self.timer += delta_time;
+        if !HOT_RELOAD || self.timer < Self::POLL_INTERVAL {
+            return vec![]
+        }
+        self.timer = 0.0;
+        let mut changed = vec![];
+        #[cfg(target_arch = "wasm32")]
+        let _ = assets;
+        #[cfg(not(target_arch = "wasm32"))]
+        for path in assets.paths() {
+            let modified = match std::fs::metadata(std::path::Path::new(ASSET_DIR).join(path)).and_then(|meta| meta.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            if let Some(previous) = self.modified.insert(path.to_string(), modified) {
+                if previous != modified {
+                    changed.push(path.to_string());
+                }
+            }
+        }
+        changed
+    }
+}
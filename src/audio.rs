@@ -2,9 +2,12 @@ use std::{io::{BufReader, Cursor}, cell::Cell};
 
 use rodio::{*, source::SamplesConverter};
 
+use crate::assets::{Assets, Handle, Sound};
+
 pub struct Audio {
     timer: f32,
     stream: Option<(OutputStream, OutputStreamHandle)>,
+    music1_handle: Handle<Sound>,
     music1: Vec<u8>,
     music2: Vec<u8>,
     current_song: Option<Song>,
@@ -21,27 +24,29 @@ const CHURCH_TIME: f32 = 88.0;
 const BOSS_TIME: f32 = 117.0;
 
 impl Audio {
-    pub fn new() -> Self {
-        // use rodio::cpal::traits::{HostTrait};
-        // let host = cpal::default_host();
-        // let devices = host.output_devices().unwrap();
-        // let mut string = String::new();
-        // for device in devices {
-        //     string += &(device.name().unwrap() + &String::from(", "));
-        // }
-        // panic!("devices: {}", string);
-        let music1 = include_bytes!("Cursed_Church_Set_V2.mp3").to_vec();
-        // let music2 = include_bytes!("Cursed_Church_Boss.mp3").to_vec();
+    pub fn new(assets: &mut Assets) -> anyhow::Result<Self> {
+        let music1 = assets.load_sound("Cursed_Church_Set_V2.mp3")?;
+        // let music2 = assets.load_sound("Cursed_Church_Boss.mp3")?;
         let music2 = vec![];
-        // stream_handle.play_raw(music1.convert_samples()).unwrap();
 
-        Self {
+        Ok(Self {
             timer: 0.0,
             stream: None,
-            music1,
+            music1_handle: music1,
+            music1: assets.sound(music1).bytes.clone(),
             music2,
             sink: None,
             current_song: None,
+        })
+    }
+
+    // takes the new bytes of a reloaded sound, restarting the song if it was playing
+    pub fn reload(&mut self, assets: &Assets, sound: Handle<Sound>) {
+        if sound == self.music1_handle {
+            self.music1 = assets.sound(sound).bytes.clone();
+            if let Some(Song::Church) = self.current_song {
+                self.play(Song::Church);
+            }
         }
     }
 
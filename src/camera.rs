@@ -1,7 +1,6 @@
-use std::collections::HashSet;
+use cgmath::{Zero, EuclideanSpace, Matrix4, Point2, Vector2};
 
-use cgmath::{Zero, EuclideanSpace, Matrix4};
-use winit::event::{WindowEvent, KeyboardInput, VirtualKeyCode, ElementState};
+use crate::{input::{ActionState, Action}, tuning::Tuning};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -15,31 +14,91 @@ pub struct Camera {
     pub center: cgmath::Point2<f32>,
     pub zoom: f32,
     pub window_size: cgmath::Vector2<u32>,
+    // draws the world at VIRTUAL_SIZE and scales it up by a whole number, so pixel art stays crisp
+    pub pixel_perfect: bool,
 }
 
 impl Camera {
+    // the resolution the world is drawn at in pixel perfect mode
+    pub const VIRTUAL_SIZE: (u32, u32) = (480, 270);
+
     pub fn new(window_size: cgmath::Vector2<u32>, zoom: f32) -> Self {
         Self {
             center: cgmath::Point2::new(0.0, 0.0),
             zoom,
             window_size,
+            pixel_perfect: false,
         }
     }
 
+    // size in pixels of what the world is drawn into, the window or the offscreen target
+    pub fn render_size(&self) -> cgmath::Vector2<u32> {
+        if self.pixel_perfect {
+            cgmath::Vector2::new(Self::VIRTUAL_SIZE.0, Self::VIRTUAL_SIZE.1)
+        } else {
+            self.window_size
+        }
+    }
+
+    // the largest whole number the offscreen target can be scaled by and still fit the window
+    pub fn upscale(&self) -> u32 {
+        if !self.pixel_perfect {
+            return 1
+        }
+        let size = self.render_size();
+        u32::max(1, u32::min(self.window_size.x / size.x, self.window_size.y / size.y))
+    }
+
+    // where the top left of the scaled up world is in the window, centered between the letterbox bars
+    pub fn letterbox_offset(&self) -> cgmath::Vector2<f32> {
+        let scaled = self.render_size() * self.upscale();
+        cgmath::Vector2::new(
+            ((self.window_size.x as i32 - scaled.x as i32) / 2) as f32,
+            ((self.window_size.y as i32 - scaled.y as i32) / 2) as f32,
+        )
+    }
+
+    // pixels per world unit, a whole number in pixel perfect mode so every texel covers the same number of pixels
     pub fn zoom_factor(&self) -> f32 {
-        std::cmp::min(self.window_size.x, self.window_size.y) as f32 / f32::max(0.001, self.zoom)
+        let size = self.render_size();
+        let zoom_factor = std::cmp::min(size.x, size.y) as f32 / f32::max(0.001, self.zoom);
+        if self.pixel_perfect {
+            f32::max(1.0, zoom_factor.round())
+        } else {
+            zoom_factor
+        }
+    }
+
+    // the center the world is drawn around, snapped to a whole pixel in pixel perfect mode so sprites don't shimmer as it moves
+    pub fn view_center(&self) -> cgmath::Point2<f32> {
+        if self.pixel_perfect {
+            let zoom_factor = self.zoom_factor();
+            cgmath::Point2::new((self.center.x * zoom_factor).round() / zoom_factor, (self.center.y * zoom_factor).round() / zoom_factor)
+        } else {
+            self.center
+        }
+    }
+
+    // half the size of the view in world units
+    pub fn half_extent(&self) -> cgmath::Vector2<f32> {
+        self.camera_center_offset() / self.zoom_factor()
     }
 
+    // the middle of what the world is drawn into
     pub fn camera_center_offset(&self) -> cgmath::Vector2<f32> {
+        let size = self.render_size();
         cgmath::Vector2::new(
-            (self.window_size.x as f32) / 2.0,
-            (self.window_size.y as f32) / 2.0
+            (size.x as f32) / 2.0,
+            (size.y as f32) / 2.0
         )
     }
 
+    fn ortho(size: cgmath::Vector2<u32>) -> cgmath::Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * cgmath::ortho(0.0, size.x as f32, size.y as f32, 0.0, 0.0, 1.0)
+    }
+
     pub fn proj(&self) -> cgmath::Matrix4<f32> {
-        let proj = OPENGL_TO_WGPU_MATRIX * cgmath::ortho(0.0, self.window_size.x as f32, self.window_size.y as f32, 0.0, 0.0, 1.0);
-        proj
+        Self::ortho(self.window_size)
     }
 
     pub fn get_ui_camera(&self) -> UICamera {
@@ -51,30 +110,36 @@ impl Camera {
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
         let zoom_factor = self.zoom_factor();
         let camera_center_offset = self.camera_center_offset();
+        let center = self.view_center();
         // let proj = cgmath::Matrix4::identity();
         // let view = cgmath::Matrix4::identity();
-        let proj = self.proj();
+        let proj = Self::ortho(self.render_size());
         let view = 
             cgmath::Matrix4::from_translation(cgmath::Vector3::new(camera_center_offset.x, camera_center_offset.y, 0.0)) *
             cgmath::Matrix4::from_scale(zoom_factor) *
-            cgmath::Matrix4::from_translation(cgmath::Vector3::new(-self.center.x, -self.center.y, 0.0));
+            cgmath::Matrix4::from_translation(cgmath::Vector3::new(-center.x, -center.y, 0.0));
         return OPENGL_TO_WGPU_MATRIX * proj * view;
     }
 
+    // window pixels to pixels of what the world is drawn into, undoing the letterbox and upscale
+    fn window_to_render(&self, position: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        (position - self.letterbox_offset()) / self.upscale() as f32
+    }
+
     pub fn view_to_world_pos(&self, position: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
-        self.center + ((position - self.camera_center_offset()).to_vec() / self.zoom_factor())
+        self.view_center() + ((self.window_to_render(position) - self.camera_center_offset()).to_vec() / self.zoom_factor())
     }
     
     pub fn view_to_world_scale(&self, scale: cgmath::Vector2<f32>) -> cgmath::Vector2<f32> {
-        scale / self.zoom_factor()
+        scale / (self.zoom_factor() * self.upscale() as f32)
     }
 
     pub fn world_to_view_pos(&self, position: cgmath::Point2<f32>) -> cgmath::Vector2<f32> {
-        (position - self.center) * self.zoom_factor() + self.camera_center_offset()
+        ((position - self.view_center()) * self.zoom_factor() + self.camera_center_offset()) * self.upscale() as f32 + self.letterbox_offset()
     }
     
     pub fn world_to_view_scale(&self, scale: cgmath::Vector2<f32>) -> cgmath::Vector2<f32> {
-        scale * self.zoom_factor()
+        scale * self.zoom_factor() * self.upscale() as f32
     }
 }
 
@@ -99,54 +164,230 @@ impl CameraObj for UICamera {
 }
 
 pub struct CameraController {
+    // views per second, so panning feels the same at any zoom
     speed: f32,
-    inputs: HashSet<VirtualKeyCode>,
-    relevant_inputs: HashSet<VirtualKeyCode>,
 }
 
 impl CameraController {
+    // each scroll line zooms by this factor
+    const ZOOM_STEP: f32 = 1.15;
+    const MIN_ZOOM: f32 = 2.0;
+    const MAX_ZOOM: f32 = 200.0;
+
     pub fn new(speed: f32) -> Self {
-        use VirtualKeyCode::*;
         Self {
             speed,
-            inputs: Default::default(),
-            relevant_inputs: vec![W, A, S, D, Space, LShift].into_iter().collect(),
         }
     }
 
-    pub fn process_events(&mut self, event: &WindowEvent) -> bool {
-        // match event {
-        //     WindowEvent::KeyboardInput {
-        //         input: KeyboardInput {
-        //             state,
-        //             virtual_keycode: Some(key), ..
-        //         }, ..
-        //     } if self.relevant_inputs.contains(&key) => {
-        //         match state {
-        //             ElementState::Pressed => self.inputs.insert(*key),
-        //             ElementState::Released => self.inputs.remove(key),
-        //         };
-        //         true
-        //     },
-        //     _ => false,
-        // }
-        false
-    }
-
-    pub fn update_camera(&self, delta_time: f32, camera: &mut Camera) {
+    pub fn update_camera(&self, delta_time: f32, camera: &mut Camera, actions: &ActionState) {
         use cgmath::InnerSpace;
-        use VirtualKeyCode::{W, A, S, D};
-        use cgmath::Vector2;
 
         let mut dir = Vector2::zero();
-        if self.inputs.contains(&W) { dir -= Vector2::unit_y(); }
-        if self.inputs.contains(&S) { dir += Vector2::unit_y(); }
-        if self.inputs.contains(&D) { dir += Vector2::unit_x(); }
-        if self.inputs.contains(&A) { dir -= Vector2::unit_x(); }
+        if actions.is_down(Action::MoveUp) { dir -= Vector2::unit_y(); }
+        if actions.is_down(Action::MoveDown) { dir += Vector2::unit_y(); }
+        if actions.is_down(Action::MoveRight) { dir += Vector2::unit_x(); }
+        if actions.is_down(Action::MoveLeft) { dir -= Vector2::unit_x(); }
         if dir != Vector2::zero() {
             dir = dir.normalize();
-            let change = dir * self.speed * delta_time;
+            let change = dir * self.speed * camera.zoom * delta_time;
             camera.center += change;
         }
     }
+
+    // zooms in for scrolling away from the user, keeping the point under the cursor in place
+    pub fn zoom_camera(&self, camera: &mut Camera, lines: f32, cursor: Point2<f32>) {
+        if lines == 0.0 {
+            return
+        }
+        let before = camera.view_to_world_pos(cursor);
+        camera.zoom = (camera.zoom * Self::ZOOM_STEP.powf(-lines)).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        camera.center += before - camera.view_to_world_pos(cursor);
+    }
+}
+
+// moves the camera during play: follows a target through a dead zone with some look ahead,
+// stays inside the level, eases between zooms and shakes with trauma
+pub struct CameraFollow {
+    // where the camera would be without shake, none until the first update snaps it to the target
+    center: Option<Point2<f32>>,
+    // the point the dead zone is around, it only moves when the target leaves the dead zone
+    focus: Point2<f32>,
+    look_ahead: Vector2<f32>,
+    // 0 to 1, the shake is trauma squared so small hits stay subtle
+    trauma: f32,
+    shake_time: f32,
+    // the zoom the camera eases towards
+    zoom: f32,
+}
+
+impl CameraFollow {
+    pub fn new() -> Self {
+        Self {
+            center: None,
+            focus: Point2::new(0.0, 0.0),
+            look_ahead: Vector2::zero(),
+            trauma: 0.0,
+            shake_time: 0.0,
+            zoom: 20.0,
+        }
+    }
+
+    pub fn zoom_to(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
+
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    // picks up from wherever the camera is now, e.g. after flying it around the editor
+    pub fn track(&mut self, camera: &Camera) {
+        self.center = Some(camera.center);
+        self.focus = camera.center;
+        self.look_ahead = Vector2::zero();
+    }
+
+    pub fn update(
+        &mut self,
+        delta_time: f32,
+        camera: &mut Camera,
+        target: Point2<f32>,
+        velocity: Vector2<f32>,
+        bounds: Option<(Vector2<f32>, Vector2<f32>)>,
+        tuning: &Tuning,
+    ) {
+        let dead_zone = Vector2::new(tuning.camera_dead_zone_x, tuning.camera_dead_zone_y);
+        let center = match self.center {
+            Some(center) => {
+                camera.zoom += (self.zoom - camera.zoom) * ease(tuning.camera_zoom_speed, delta_time);
+                self.focus = follow_dead_zone(self.focus, target, dead_zone);
+                self.look_ahead += (velocity * tuning.camera_look_ahead - self.look_ahead) * ease(tuning.camera_follow_speed, delta_time);
+                center + (self.focus + self.look_ahead - center) * ease(tuning.camera_follow_speed, delta_time)
+            },
+            None => {
+                camera.zoom = self.zoom;
+                self.focus = target;
+                target
+            },
+        };
+        let center = match bounds {
+            Some((min, max)) => clamp_to_bounds(center, camera.half_extent(), min, max),
+            None => center,
+        };
+        self.center = Some(center);
+
+        self.trauma = f32::max(0.0, self.trauma - tuning.camera_shake_decay * delta_time);
+        self.shake_time += delta_time;
+        camera.center = center + shake_offset(self.shake_time, self.trauma) * tuning.camera_shake_offset;
+    }
+}
+
+// the fraction of the way to its goal something easing at rate covers in delta_time, independent of frame rate
+pub fn ease(rate: f32, delta_time: f32) -> f32 {
+    1.0 - (-rate * delta_time).exp()
+}
+
+// moves focus as little as possible to bring target within half_size of it on each axis
+pub fn follow_dead_zone(focus: Point2<f32>, target: Point2<f32>, half_size: Vector2<f32>) -> Point2<f32> {
+    Point2::new(
+        focus.x.clamp(target.x - half_size.x, target.x + half_size.x),
+        focus.y.clamp(target.y - half_size.y, target.y + half_size.y),
+    )
+}
+
+// keeps a view of half_extent around center inside min to max, centering it on any axis the bounds are too small for
+pub fn clamp_to_bounds(center: Point2<f32>, half_extent: Vector2<f32>, min: Vector2<f32>, max: Vector2<f32>) -> Point2<f32> {
+    let clamp_axis = |center: f32, half: f32, min: f32, max: f32| if max - min <= half * 2.0 {
+        (min + max) / 2.0
+    } else {
+        center.clamp(min + half, max - half)
+    };
+    Point2::new(
+        clamp_axis(center.x, half_extent.x, min.x, max.x),
+        clamp_axis(center.y, half_extent.y, min.y, max.y),
+    )
+}
+
+// a smooth wobble up to trauma squared in each direction, from sines at unrelated frequencies so it doesn't visibly repeat
+pub fn shake_offset(time: f32, trauma: f32) -> Vector2<f32> {
+    let wobble = |a: f32, b: f32, phase: f32| (time * a + phase).sin() * 0.6 + (time * b + phase * 2.3).sin() * 0.4;
+    Vector2::new(wobble(31.0, 47.3, 0.0), wobble(37.7, 53.1, 1.9)) * trauma * trauma
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use super::*;
+
+    #[test]
+    fn view_and_world_positions_round_trip() {
+        // neither window is a whole multiple of VIRTUAL_SIZE, so pixel perfect mode letterboxes both
+        for window_size in [Vector2::new(1366, 768), Vector2::new(1000, 700)] {
+            for pixel_perfect in [false, true] {
+                let mut camera = Camera::new(window_size, 20.0);
+                camera.pixel_perfect = pixel_perfect;
+                camera.center = Point2::new(3.3, -1.7);
+                for p in [Point2::new(0.0, 0.0), Point2::new(-12.25, 7.5), Point2::new(4.1, -3.9)] {
+                    let round_trip = camera.view_to_world_pos(Point2::from_vec(camera.world_to_view_pos(p)));
+                    assert!((round_trip - p).magnitude() < 1e-3,
+                        "{:?} came back as {:?} in a {:?} window, pixel perfect {}", p, round_trip, window_size, pixel_perfect);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dead_zone_moves_the_focus_as_little_as_possible() {
+        let focus = Point2::new(0.0, 0.0);
+        let half_size = Vector2::new(2.0, 1.0);
+        // inside or right on the edge, the focus stays
+        assert_eq!(follow_dead_zone(focus, Point2::new(1.5, -0.5), half_size), focus);
+        assert_eq!(follow_dead_zone(focus, Point2::new(2.0, 1.0), half_size), focus);
+        assert_eq!(follow_dead_zone(focus, Point2::new(-2.0, -1.0), half_size), focus);
+        // past the edge, the focus moves just enough to put the target back on it
+        assert_eq!(follow_dead_zone(focus, Point2::new(3.0, 0.0), half_size), Point2::new(1.0, 0.0));
+        assert_eq!(follow_dead_zone(focus, Point2::new(-5.0, 4.0), half_size), Point2::new(-3.0, 3.0));
+        // no dead zone follows exactly
+        assert_eq!(follow_dead_zone(focus, Point2::new(7.0, -7.0), Vector2::zero()), Point2::new(7.0, -7.0));
+    }
+
+    #[test]
+    fn bounds_keep_the_view_inside() {
+        let half_extent = Vector2::new(5.0, 3.0);
+        let (min, max) = (Vector2::new(-20.0, -10.0), Vector2::new(20.0, 10.0));
+        assert_eq!(clamp_to_bounds(Point2::new(0.0, 0.0), half_extent, min, max), Point2::new(0.0, 0.0));
+        assert_eq!(clamp_to_bounds(Point2::new(19.0, -9.0), half_extent, min, max), Point2::new(15.0, -7.0));
+        assert_eq!(clamp_to_bounds(Point2::new(-30.0, 30.0), half_extent, min, max), Point2::new(-15.0, 7.0));
+    }
+
+    #[test]
+    fn bounds_narrower_than_the_view_are_centered() {
+        let half_extent = Vector2::new(5.0, 3.0);
+        // 6 wide is narrower than the 10 wide view, 6 tall is exactly as tall as it
+        let (min, max) = (Vector2::new(2.0, -10.0), Vector2::new(8.0, -4.0));
+        for center in [Point2::new(-100.0, 100.0), Point2::new(5.0, -7.0), Point2::new(100.0, -100.0)] {
+            assert_eq!(clamp_to_bounds(center, half_extent, min, max), Point2::new(5.0, -7.0));
+        }
+    }
+
+    #[test]
+    fn trauma_decays_to_zero() {
+        let tuning = Tuning::default();
+        let mut camera = Camera::new(Vector2::new(800, 600), 20.0);
+        let mut follow = CameraFollow::new();
+        let target = Point2::new(1.0, 2.0);
+        follow.add_trauma(0.7);
+        follow.add_trauma(0.7);
+        assert_eq!(follow.trauma, 1.0);
+        follow.update(0.01, &mut camera, target, Vector2::zero(), None, &tuning);
+        assert!(follow.trauma < 1.0 && follow.trauma > 0.0);
+        for _ in 0..1000 {
+            follow.update(0.1, &mut camera, target, Vector2::zero(), None, &tuning);
+        }
+        assert_eq!(follow.trauma, 0.0);
+        // without trauma the camera sits where it follows to
+        assert!((camera.center - target).magnitude() < 1e-3);
+    }
 }
@@ -1,14 +1,71 @@
+use std::collections::VecDeque;
+
 use cgmath::{Vector4, Vector2, Matrix4, SquareMatrix, Vector3};
 
-use crate::graphics::{text::{FontMetricsInfo, FontInfoContainer, BaseFontInfoContainer}, textured};
+use crate::{graphics::{text::{FontMetricsInfo, FontInfoContainer, BaseFontInfoContainer}, textured}, line_editor::LineEditor, util::format_time};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageCategory {
+    System,
+    // output of console commands
+    Command,
+    // lines typed into the chatbox
+    Chat,
+    Warning,
+    Error,
+}
+
+impl MessageCategory {
+    pub fn all() -> Vec<MessageCategory> {
+        use MessageCategory::*;
+        vec![System, Command, Chat, Warning, Error]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MessageCategory::System => "system",
+            MessageCategory::Command => "command",
+            MessageCategory::Chat => "chat",
+            MessageCategory::Warning => "warning",
+            MessageCategory::Error => "error",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MessageCategory> {
+        MessageCategory::all().into_iter().find(|category| category.name() == name)
+    }
+
+    pub fn color(&self) -> Vector4<f32> {
+        match *self {
+            MessageCategory::System => Vector4::new(0.7, 0.8, 1.0, 1.0),
+            MessageCategory::Command => Vector4::new(1.0, 1.0, 1.0, 1.0),
+            MessageCategory::Chat => Vector4::new(0.8, 1.0, 0.8, 1.0),
+            MessageCategory::Warning => Vector4::new(1.0, 0.85, 0.3, 1.0),
+            MessageCategory::Error => Vector4::new(1.0, 0.4, 0.4, 1.0),
+        }
+    }
+}
+
+// one wrapped line of the log
+pub struct LogLine {
+    pub text: String,
+    pub category: MessageCategory,
+}
 
 pub struct Chatbox {
     font_info: FontMetricsInfo,
     visible_lines: i32,
     line_height: f32,
     history_length: i32,
-    typing: String,
-    history: Vec<String>,
+    typing: LineEditor,
+    // oldest first, drops the oldest lines past history_length
+    history: VecDeque<LogLine>,
+    // categories shown, all of them by default
+    filter: Vec<MessageCategory>,
+    // lines scrolled up from the newest
+    scroll: usize,
+    // seconds since the chatbox was created, for timestamps
+    clock: f32,
     width: f32,
     height: f32,
     flicker_timer: f32,
@@ -28,8 +85,11 @@ impl Chatbox {
             visible_lines,
             line_height,
             history_length,
-            typing: String::new(),
-            history: Vec::new(),
+            typing: LineEditor::new(),
+            history: VecDeque::new(),
+            filter: MessageCategory::all(),
+            scroll: 0,
+            clock: 0.0,
             width,
             height: (visible_lines + 1) as f32 * line_height,
             flicker_timer: 0.0,
@@ -39,40 +99,88 @@ impl Chatbox {
     }
 
     pub fn println(&mut self, line: &str) {
-        println!("{}", line);
-        let mut lines: Vec<String> = self.font_info.split_lines(line, Some(self.width));
-        let add_len = std::cmp::min(self.history_length as usize, lines.len()) as i32;
-        lines.drain(0..(std::cmp::max(0, lines.len() as i32 - add_len)) as usize);
-        let history_remove = 
-            std::cmp::max(0, self.history.len() as i32 - (self.history_length - add_len)) as usize;
-        self.history.drain(0..history_remove);
-        self.history.append(&mut lines);
-        self.fade_timer = 0.0;
+        self.print(MessageCategory::System, line);
+    }
+
+    // adds a timestamped message, word wrapped to the chatbox width
+    pub fn print(&mut self, category: MessageCategory, line: &str) {
+        let line = format!("[{}] {}", format_time(self.clock), line);
+        for text in self.font_info.split_lines(&line, Some(self.width)) {
+            self.history.push_back(LogLine { text, category });
+        }
+        while self.history.len() > self.history_length as usize {
+            self.history.pop_front();
+        }
+        self.scroll = usize::min(self.scroll, self.max_scroll());
+        if self.filter.contains(&category) {
+            self.fade_timer = 0.0;
+        }
     }
 
-    fn get_visible_history_empty_lines(&self) -> i32 {
-        std::cmp::max(0, self.visible_lines - self.history.len() as i32)
+    pub fn set_filter(&mut self, filter: Vec<MessageCategory>) {
+        self.filter = filter;
+        self.scroll = usize::min(self.scroll, self.max_scroll());
     }
 
-    pub fn get_visible_history(&self) -> Vec<&str> {
-        let mut vec = Vec::new();
-        for i in (std::cmp::max(0, self.history.len() as i32 - self.visible_lines) as usize)..self.history.len() {
-            vec.push(self.history[i].as_str());
+    fn filtered_history(&self) -> impl DoubleEndedIterator<Item = &LogLine> {
+        self.history.iter().filter(|line| self.filter.contains(&line.category))
+    }
+
+    // the top row shows how far back the log is scrolled instead of a line while scrolled
+    fn lines_in_view(&self) -> usize {
+        let visible_lines = self.visible_lines as usize;
+        if self.scroll > 0 { visible_lines.saturating_sub(1) } else { visible_lines }
+    }
+
+    // far enough back that the oldest line shows in the rows left under the scroll label
+    fn max_scroll(&self) -> usize {
+        let count = self.filtered_history().count();
+        if count <= self.visible_lines as usize {
+            0
+        } else {
+            count - (self.visible_lines as usize).saturating_sub(1)
         }
-        vec
     }
 
-    pub fn get_typing(&self) -> &String {
-        &self.typing
+    // positive lines scroll towards older messages
+    pub fn scroll(&mut self, lines: i32) {
+        let scroll = self.scroll as i64 + lines as i64;
+        self.scroll = i64::clamp(scroll, 0, self.max_scroll() as i64) as usize;
+        self.fade_timer = 0.0;
+    }
+
+    pub fn scroll_page(&mut self, pages: i32) {
+        self.scroll(pages * self.visible_lines);
     }
 
-    pub fn add_typing(&mut self, c: char) {
-        self.typing.push(c);
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll = 0;
     }
 
-    pub fn remove_typing(&mut self, count: i32) {
-        assert!(count >= 0);
-        self.typing.truncate(std::cmp::max(0, self.typing.len() as i32 - count) as usize);
+    // the filtered lines in view after scrolling, oldest first
+    pub fn get_visible_history(&self) -> Vec<&LogLine> {
+        let mut lines: Vec<&LogLine> = self.filtered_history()
+            .rev()
+            .skip(self.scroll)
+            .take(self.lines_in_view())
+            .collect();
+        lines.reverse();
+        lines
+    }
+
+    pub fn get_typing(&self) -> &str {
+        self.typing.text()
+    }
+
+    // caret movement, selection and editing of the typing line
+    pub fn typing_editor(&mut self) -> &mut LineEditor {
+        // keep the caret visible while editing
+        self.flicker_timer = BAR_FLICKER_TIME / 2.0;
+        &mut self.typing
+    }
+
+    pub fn set_typing(&mut self, typing: &str) {
+        self.typing.set_text(typing);
     }
 
     pub fn erase_typing(&mut self) {
@@ -86,6 +194,7 @@ impl Chatbox {
     }
 
     pub fn update(&mut self, delta_time: f32) {
+        self.clock += delta_time;
         self.fade_timer += delta_time;
         if self.typing_flicker {
             self.flicker_timer += delta_time;
@@ -95,7 +204,8 @@ impl Chatbox {
         }
     }
 
-    pub fn render(&self) -> (textured::Instance, Vec<(String, Vector2<f32>, Vector4<f32>)>) {
+    // background and selection rects, then text
+    pub fn render(&self) -> (Vec<textured::Instance>, Vec<(String, Vector2<f32>, Vector4<f32>)>) {
         let is_fade = self.fade_timer > FADE_START_TIME && !self.typing_flicker;
         let mut fade = 1.0;
         if is_fade {
@@ -110,24 +220,137 @@ impl Chatbox {
             color: background_color,
             position: Vector2::new(position.x + self.width / 2.0, position.y + self.height / 2.0),
             scale: Vector2::new(self.width, self.height),
+            ..Default::default()
         };
         
+        let visible = self.get_visible_history();
+        let empty_lines = self.visible_lines - visible.len() as i32;
         let start = Vector2::new(
             position.x,
-            position.y + self.line_height * (self.get_visible_history_empty_lines() + 1) as f32
+            position.y + self.line_height * (empty_lines + 1) as f32
         );
-        let (pos, mut instances) = self.get_visible_history().iter().fold((start, vec![]), |(mut pos, mut instances), line| {
-            instances.push((line.to_string(), pos, color));
+        let (pos, mut instances) = visible.iter().fold((start, vec![]), |(mut pos, mut instances), line| {
+            instances.push((line.text.clone(), pos, line.category.color() * fade));
             pos += Vector2::new(0.0, self.line_height);
             (pos, instances)
         });
+        // say how far back the log is scrolled in the top right corner
+        if self.scroll > 0 {
+            let text = format!("+{} newer", self.scroll);
+            let x = position.x + self.width - self.font_info.text_width(&text);
+            // text is positioned by its baseline, so this is the top row, which no line is in while scrolled
+            instances.push((text, Vector2::new(x, position.y + self.line_height), color * 0.7));
+        }
 
-        let typing_line = if self.flicker_timer > BAR_FLICKER_TIME / 2.0 && self.typing_flicker {
-            self.typing.to_owned() + "|"
-        } else {
-            self.typing.to_owned()
+        let text = self.typing.text();
+        let mut rects = vec![background_instance];
+        if let Some(range) = self.typing.selection() {
+            let start = self.font_info.text_width(&text[..range.start]);
+            let width = self.font_info.text_width(&text[range]);
+            rects.push(textured::Instance {
+                color: Vector4::new(0.3, 0.5, 1.0, 0.6) * fade,
+                position: Vector2::new(pos.x + start + width / 2.0, pos.y - self.line_height * 0.3),
+                scale: Vector2::new(width, self.line_height),
+                ..Default::default()
+            });
+        }
+        instances.push((text.to_string(), pos, color));
+        // the caret is drawn on its own so the text doesn't shift as it flickers
+        if self.flicker_timer >= BAR_FLICKER_TIME / 2.0 && self.typing_flicker {
+            let caret_x = self.font_info.text_width(&text[..self.typing.caret()]);
+            instances.push(("|".to_string(), pos + Vector2::new(caret_x, 0.0), color));
+        }
+        (rects, instances)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::graphics::text::GlyphMetrics;
+
+    use super::*;
+
+    // every printable ascii character one unit wide
+    fn font() -> FontMetricsInfo {
+        let metrics = GlyphMetrics {
+            glyph_pos: Vector2::new(0.0, 0.0),
+            glyph_size: Vector2::new(1.0, 1.0),
+            advance: 1.0,
+            lsb: 0.0,
+            tsb: 0.0,
         };
-        instances.push((typing_line, pos, color));
-        (background_instance, instances)
+        FontMetricsInfo {
+            char_data: (' '..='~').map(|c| (c, metrics.clone())).collect::<HashMap<_, _>>(),
+            font_size: 10.0,
+            height: 10.0,
+        }
+    }
+
+    // a chatbox showing 3 lines that keeps 10
+    fn chatbox(lines: &[(MessageCategory, &str)]) -> Chatbox {
+        let mut chatbox = Chatbox::new(font(), 3, 10.0, 10, 1000.0);
+        for (category, line) in lines {
+            chatbox.print(*category, line);
+        }
+        chatbox
+    }
+
+    fn visible(chatbox: &Chatbox) -> Vec<String> {
+        chatbox.get_visible_history().iter().map(|line| line.text.trim_start_matches("[0:00] ").to_string()).collect()
+    }
+
+    #[test]
+    fn history_drops_the_oldest_lines() {
+        let lines: Vec<String> = (0..12).map(|i| i.to_string()).collect();
+        let chatbox = chatbox(&lines.iter().map(|line| (MessageCategory::System, line.as_str())).collect::<Vec<_>>());
+        assert_eq!(chatbox.history.len(), 10);
+        assert_eq!(visible(&chatbox), vec!["9", "10", "11"]);
+    }
+
+    #[test]
+    fn scrolling_is_clamped_and_leaves_the_top_row_for_the_label() {
+        let lines: Vec<String> = (0..6).map(|i| i.to_string()).collect();
+        let mut chatbox = chatbox(&lines.iter().map(|line| (MessageCategory::System, line.as_str())).collect::<Vec<_>>());
+        chatbox.scroll(1);
+        assert_eq!(visible(&chatbox), vec!["3", "4"]);
+        chatbox.scroll(100);
+        // the oldest line is in the bottom of the two rows left
+        assert_eq!(chatbox.scroll, 4);
+        assert_eq!(visible(&chatbox), vec!["0", "1"]);
+        chatbox.scroll(-100);
+        assert_eq!(chatbox.scroll, 0);
+        assert_eq!(visible(&chatbox), vec!["3", "4", "5"]);
+    }
+
+    #[test]
+    fn scroll_label_has_its_own_row() {
+        let lines: Vec<String> = (0..6).map(|i| i.to_string()).collect();
+        let mut chatbox = chatbox(&lines.iter().map(|line| (MessageCategory::System, line.as_str())).collect::<Vec<_>>());
+        chatbox.scroll(2);
+        let (_, text) = chatbox.render();
+        let label = text.iter().find(|(text, _, _)| text == "+2 newer").expect("scrolled back, so the label shows");
+        let lines: Vec<_> = text.iter().filter(|(text, _, _)| text.starts_with('[')).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|(_, position, _)| position.y > label.1.y));
+    }
+
+    #[test]
+    fn filter_hides_categories_and_clamps_the_scroll() {
+        let mut chatbox = chatbox(&[
+            (MessageCategory::System, "a"),
+            (MessageCategory::Error, "b"),
+            (MessageCategory::Chat, "c"),
+            (MessageCategory::Error, "d"),
+            (MessageCategory::System, "e"),
+            (MessageCategory::Error, "f"),
+        ]);
+        chatbox.scroll(100);
+        chatbox.set_filter(vec![MessageCategory::Error]);
+        assert_eq!(chatbox.scroll, 0);
+        assert_eq!(visible(&chatbox), vec!["b", "d", "f"]);
+        chatbox.scroll(1);
+        assert_eq!(chatbox.scroll, 0);
     }
 }
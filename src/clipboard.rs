@@ -0,0 +1,67 @@
+// system clipboard for text, falling back to a clipboard private to the game when the system
+// one can't be opened (on the web, or without a display server)
+
+pub struct Clipboard {
+    #[cfg(not(target_arch = "wasm32"))]
+    system: Option<arboard::Clipboard>,
+    // only the first failed read is logged, pasting again usually fails the same way and quietly uses fallback
+    #[cfg(not(target_arch = "wasm32"))]
+    warned: bool,
+    fallback: String,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            system: arboard::Clipboard::new()
+                .map_err(|err| log::warn!("Couldn't open the system clipboard: {}", err))
+                .ok(),
+            #[cfg(not(target_arch = "wasm32"))]
+            warned: false,
+            fallback: String::new(),
+        }
+    }
+
+    // only ever uses the game's own clipboard, so tests don't touch the system one
+    pub fn private() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            system: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            warned: false,
+            fallback: String::new(),
+        }
+    }
+
+    pub fn get(&mut self) -> String {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(system) = &mut self.system {
+            match system.get_text() {
+                Ok(text) => return text,
+                Err(err) if !self.warned => {
+                    log::warn!("Couldn't read the clipboard: {}", err);
+                    self.warned = true;
+                },
+                Err(_) => (),
+            }
+        }
+        self.fallback.clone()
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.fallback = text.to_string();
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(system) = &mut self.system {
+            if let Err(err) = system.set_text(text) {
+                log::warn!("Couldn't write the clipboard: {}", err);
+            }
+        }
+    }
+}
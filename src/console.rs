@@ -0,0 +1,372 @@
+// command registry for the chatbox console. commands declare typed arguments so parsing,
+// usage errors, help text and tab completion all come from one place.
+
+#[derive(Clone, Copy)]
+pub enum ArgType {
+    Int,
+    Float,
+    Bool,
+    Word,
+    // a word from a list, used for completion and validation
+    Choice(fn() -> Vec<String>),
+}
+
+impl ArgType {
+    fn describe(&self) -> &'static str {
+        match self {
+            ArgType::Int => "int",
+            ArgType::Float => "float",
+            ArgType::Bool => "bool",
+            ArgType::Word | ArgType::Choice(_) => "word",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub typ: ArgType,
+    pub optional: bool,
+}
+
+pub const fn arg(name: &'static str, typ: ArgType) -> ArgSpec {
+    ArgSpec { name, typ, optional: false }
+}
+
+pub const fn optional(name: &'static str, typ: ArgType) -> ArgSpec {
+    ArgSpec { name, typ, optional: true }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgValue {
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Word(String),
+}
+
+// parsed arguments, accessors panic on a type mismatch since parsing already checked the spec
+pub struct Args {
+    values: Vec<ArgValue>,
+}
+
+impl Args {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn int(&self, i: usize) -> i64 {
+        match self.values[i] {
+            ArgValue::Int(v) => v,
+            ref other => panic!("argument {} is {:?}, not an int", i, other),
+        }
+    }
+
+    pub fn float(&self, i: usize) -> f32 {
+        match self.values[i] {
+            ArgValue::Float(v) => v,
+            ArgValue::Int(v) => v as f32,
+            ref other => panic!("argument {} is {:?}, not a float", i, other),
+        }
+    }
+
+    pub fn bool(&self, i: usize) -> bool {
+        match self.values[i] {
+            ArgValue::Bool(v) => v,
+            ref other => panic!("argument {} is {:?}, not a bool", i, other),
+        }
+    }
+
+    pub fn word(&self, i: usize) -> &str {
+        match &self.values[i] {
+            ArgValue::Word(v) => v,
+            other => panic!("argument {} is {:?}, not a word", i, other),
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&ArgValue> {
+        self.values.get(i)
+    }
+}
+
+pub type Handler<C> = fn(&mut C, &Args) -> Result<(), String>;
+
+pub struct Command<C> {
+    pub name: &'static str,
+    pub args: Vec<ArgSpec>,
+    pub help: &'static str,
+    pub handler: Handler<C>,
+}
+
+impl<C> Command<C> {
+    pub fn usage(&self) -> String {
+        self.args.iter().fold(self.name.to_string(), |usage, arg| {
+            if arg.optional {
+                format!("{} [{}:{}]", usage, arg.name, arg.typ.describe())
+            } else {
+                format!("{} <{}:{}>", usage, arg.name, arg.typ.describe())
+            }
+        })
+    }
+}
+
+pub struct CommandRegistry<C> {
+    commands: Vec<Command<C>>,
+}
+
+impl<C> Default for CommandRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> CommandRegistry<C> {
+    pub fn new() -> Self {
+        Self { commands: vec![] }
+    }
+
+    pub fn register(&mut self, name: &'static str, args: Vec<ArgSpec>, help: &'static str, handler: Handler<C>) {
+        debug_assert!(self.get(name).is_none(), "command {} registered twice", name);
+        self.commands.push(Command { name, args, help, handler });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command<C>> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &Command<C>> {
+        self.commands.iter()
+    }
+
+    // parses line into a handler and its arguments, or a message saying what is wrong
+    pub fn parse(&self, line: &str) -> Result<(Handler<C>, Args), String> {
+        let tokens = tokenize(line);
+        let (name, rest) = match tokens.split_first() {
+            Some(split) => split,
+            None => return Err("Empty command".to_string()),
+        };
+        let command = self.get(name).ok_or_else(|| format!("Unknown command {}, try help", name))?;
+        let required = command.args.iter().filter(|arg| !arg.optional).count();
+        if rest.len() < required || rest.len() > command.args.len() {
+            return Err(format!("Usage: {}", command.usage()))
+        }
+        let values = rest.iter().zip(command.args.iter())
+            .map(|(token, spec)| parse_arg(token, spec)
+                .map_err(|err| format!("{}\nUsage: {}", err, command.usage())))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((command.handler, Args { values }))
+    }
+
+    // candidates for the word being typed at the end of line
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let tokens = completion_tokens(line);
+        let partial = tokens.last().cloned().unwrap_or_default();
+        let candidates: Vec<String> = if tokens.len() <= 1 {
+            self.commands.iter().map(|command| command.name.to_string()).collect()
+        } else {
+            let arg_index = tokens.len() - 2;
+            match self.get(&tokens[0]).and_then(|command| command.args.get(arg_index)) {
+                Some(ArgSpec { typ: ArgType::Choice(choices), .. }) => choices(),
+                Some(ArgSpec { typ: ArgType::Bool, .. }) => vec!["true".to_string(), "false".to_string()],
+                _ => vec![],
+            }
+        };
+        candidates.into_iter().filter(|candidate| candidate.starts_with(&partial)).collect()
+    }
+}
+
+fn parse_arg(token: &str, spec: &ArgSpec) -> Result<ArgValue, String> {
+    match spec.typ {
+        ArgType::Int => token.parse().map(ArgValue::Int)
+            .map_err(|_| format!("{} should be an int, got {}", spec.name, token)),
+        // nan and inf parse as floats, but nothing the console sets can use them
+        ArgType::Float => match token.parse::<f32>() {
+            Ok(value) if value.is_finite() => Ok(ArgValue::Float(value)),
+            Ok(_) => Err(format!("{} expected a finite number, got {}", spec.name, token)),
+            Err(_) => Err(format!("{} should be a number, got {}", spec.name, token)),
+        },
+        ArgType::Bool => match token {
+            "true" | "on" | "1" => Ok(ArgValue::Bool(true)),
+            "false" | "off" | "0" => Ok(ArgValue::Bool(false)),
+            _ => Err(format!("{} should be true or false, got {}", spec.name, token)),
+        },
+        ArgType::Word => Ok(ArgValue::Word(token.to_string())),
+        ArgType::Choice(choices) => {
+            let choices = choices();
+            if choices.iter().any(|choice| choice == token) {
+                Ok(ArgValue::Word(token.to_string()))
+            } else {
+                Err(format!("{} should be one of {}, got {}", spec.name, choices.join(", "), token))
+            }
+        },
+    }
+}
+
+// splits on whitespace, double quotes group words into one token
+pub fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            },
+            c => {
+                current.push(c);
+                has_token = true;
+            },
+        }
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    tokens
+}
+
+// the tokens of line, ending with the word being typed, which is empty after a trailing space
+pub fn completion_tokens(line: &str) -> Vec<String> {
+    let mut tokens = tokenize(line);
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        tokens.push(String::new());
+    }
+    tokens
+}
+
+// the line that tokenizes back into tokens, quoting the ones with spaces
+pub fn join_tokens(tokens: &[String]) -> String {
+    tokens.iter()
+        .map(|token| if token.contains(char::is_whitespace) { format!("\"{}\"", token) } else { token.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// longest prefix shared by all candidates
+pub fn common_prefix(candidates: &[String]) -> String {
+    let first = match candidates.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut prefix_len = first.len();
+    for candidate in &candidates[1..] {
+        prefix_len = first.char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(prefix_len);
+    }
+    first[..prefix_len].to_string()
+}
+
+// previously entered lines, browsed with up and down in the typing line
+pub struct CommandHistory {
+    lines: Vec<String>,
+    // index into lines while browsing, None when editing a fresh line
+    cursor: Option<usize>,
+    max_len: usize,
+}
+
+impl CommandHistory {
+    pub fn new(max_len: usize) -> Self {
+        Self { lines: vec![], cursor: None, max_len }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        self.cursor = None;
+        if line.is_empty() || self.lines.last().map_or(false, |last| last == line) {
+            return
+        }
+        self.lines.push(line.to_string());
+        if self.lines.len() > self.max_len {
+            self.lines.remove(0);
+        }
+    }
+
+    // older line, stays on the oldest one
+    pub fn up(&mut self) -> Option<&str> {
+        if self.lines.is_empty() {
+            return None
+        }
+        let cursor = match self.cursor {
+            Some(cursor) => cursor.saturating_sub(1),
+            None => self.lines.len() - 1,
+        };
+        self.cursor = Some(cursor);
+        Some(&self.lines[cursor])
+    }
+
+    // newer line, or an empty line after the newest one
+    pub fn down(&mut self) -> Option<&str> {
+        match self.cursor {
+            Some(cursor) if cursor + 1 < self.lines.len() => {
+                self.cursor = Some(cursor + 1);
+                Some(&self.lines[cursor + 1])
+            },
+            Some(_) => {
+                self.cursor = None;
+                Some("")
+            },
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> CommandRegistry<()> {
+        let mut registry = CommandRegistry::default();
+        registry.register("say", vec![arg("text", ArgType::Word), optional("loud", ArgType::Bool)], "", |_, _| Ok(()));
+        registry.register("spawn", vec![], "", |_, _| Ok(()));
+        registry.register("teleport", vec![arg("x", ArgType::Float), arg("y", ArgType::Float)], "", |_, _| Ok(()));
+        registry
+    }
+
+    #[test]
+    fn completes_the_last_token() {
+        let registry = registry();
+        assert_eq!(registry.complete("s"), vec!["say", "spawn"]);
+        assert_eq!(registry.complete("say \"a b\" t"), vec!["true"]);
+        assert_eq!(registry.complete("say \"a b\" "), vec!["true", "false"]);
+    }
+
+    #[test]
+    fn joined_tokens_tokenize_back() {
+        let tokens = completion_tokens("say \"a b\" t");
+        assert_eq!(tokens, vec!["say", "a b", "t"]);
+        assert_eq!(tokenize(&join_tokens(&tokens)), tokens);
+    }
+
+    #[test]
+    fn floats_must_be_finite() {
+        let registry = registry();
+        let (_, args) = registry.parse("teleport -1.5 2").unwrap();
+        assert_eq!((args.float(0), args.float(1)), (-1.5, 2.0));
+        for bad in ["nan", "inf", "-inf", "NaN", "infinity"] {
+            let err = registry.parse(&format!("teleport {} 0", bad)).err().unwrap();
+            assert!(err.starts_with(&format!("x expected a finite number, got {}", bad)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn empty_args() {
+        let (_, args) = registry().parse("spawn").unwrap();
+        assert!(args.is_empty());
+        let (_, args) = registry().parse("say hi").unwrap();
+        assert_eq!(args.len(), 1);
+    }
+}
@@ -0,0 +1,306 @@
+use cgmath::{Point2, EuclideanSpace, Vector2, Vector4};
+use winit::event::MouseButton;
+
+use crate::{State, world::{World, stage}, input::Action, ui::Ui, highscore::HighScores, graphics::ResolveInstance};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameState {
+    Title,
+    Playing,
+    // pushed on top of Playing so resuming pops back to it
+    Paused,
+    GameOver,
+    LevelComplete,
+    // pushed on top of whatever was running
+    Editor,
+}
+
+// requests to move between game states, from the world, input or console commands
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameStateChange {
+    Start,
+    PlayerLose,
+    LevelComplete,
+    NextLevel,
+    Restart,
+    Pause,
+    Resume,
+    ToTitle,
+    EnterEditor,
+    ExitEditor,
+}
+
+impl GameState {
+    pub fn behavior(self) -> &'static dyn GameStateBehavior {
+        match self {
+            GameState::Title => &TitleState,
+            GameState::Playing => &PlayingState,
+            GameState::Paused => &PausedState,
+            GameState::GameOver => &GameOverState,
+            GameState::LevelComplete => &LevelCompleteState,
+            GameState::Editor => &EditorState,
+        }
+    }
+}
+
+// what the render engine draws for a state on top of the world
+pub enum Overlay {
+    None,
+    // title and subtitle over a dimmed world
+    Text(String, String),
+    // the player's movement states and the editor's debug objects
+    Debug,
+}
+
+pub trait GameStateBehavior {
+    // reacts to this frame's input, a change skips the rest of the frame's update
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange>;
+
+    fn update(&self, state: &mut State, delta_time: f32, real_delta_time: f32) -> Option<GameStateChange>;
+
+    fn render(&self, world: &World) -> Overlay;
+
+    // menus drawn with the ui this frame
+    fn menu(&self, _ui: &mut Ui, _world: &World, _high_scores: &HighScores, _last_rank: Option<usize>) -> Vec<GameStateChange> {
+        vec![]
+    }
+
+    fn shows_hud(&self) -> bool {
+        true
+    }
+}
+
+pub struct TitleState;
+
+impl GameStateBehavior for TitleState {
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        state.input_state.actions.pressed(Action::Jump).then_some(GameStateChange::Start)
+    }
+
+    fn update(&self, state: &mut State, _: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        state.update_camera(real_delta_time, state.tuning.camera_zoom);
+        None
+    }
+
+    fn render(&self, _: &World) -> Overlay {
+        Overlay::Text("Cursed Church".to_string(), "Press jump to start".to_string())
+    }
+
+    fn shows_hud(&self) -> bool {
+        false
+    }
+}
+
+pub struct PlayingState;
+
+impl GameStateBehavior for PlayingState {
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        let actions = &state.input_state.actions;
+        if actions.pressed(Action::Pause) {
+            Some(GameStateChange::Pause)
+        } else if actions.pressed(Action::Reset) {
+            Some(GameStateChange::Restart)
+        } else {
+            None
+        }
+    }
+
+    fn update(&self, state: &mut State, delta_time: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        let change = state.world.update(delta_time, &state.input_state, &state.tuning);
+        state.update_camera(real_delta_time, state.tuning.camera_zoom);
+        change
+    }
+
+    fn render(&self, _: &World) -> Overlay {
+        Overlay::None
+    }
+}
+
+pub struct PausedState;
+
+impl GameStateBehavior for PausedState {
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        let actions = &state.input_state.actions;
+        if actions.pressed(Action::Pause) {
+            Some(GameStateChange::Resume)
+        } else if actions.pressed(Action::Reset) {
+            Some(GameStateChange::ToTitle)
+        } else {
+            None
+        }
+    }
+
+    fn update(&self, state: &mut State, _: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        state.update_camera(real_delta_time, state.tuning.camera_zoom);
+        None
+    }
+
+    fn render(&self, _: &World) -> Overlay {
+        Overlay::Text("Paused".to_string(), "Press pause to resume, reset to quit".to_string())
+    }
+
+    fn menu(&self, ui: &mut Ui, _: &World, _: &HighScores, _: Option<usize>) -> Vec<GameStateChange> {
+        let mut changes = vec![];
+        ui.panel(|ui| {
+            if ui.button("Resume") {
+                changes.push(GameStateChange::Resume);
+            }
+            if ui.button("Restart") {
+                changes.push(GameStateChange::Restart);
+            }
+            if ui.button("Quit to title") {
+                changes.push(GameStateChange::ToTitle);
+            }
+        });
+        changes
+    }
+}
+
+pub struct GameOverState;
+
+impl GameStateBehavior for GameOverState {
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        let actions = &state.input_state.actions;
+        if actions.pressed(Action::Jump) || actions.pressed(Action::Reset) {
+            Some(GameStateChange::Restart)
+        } else if actions.pressed(Action::Pause) {
+            Some(GameStateChange::ToTitle)
+        } else {
+            None
+        }
+    }
+
+    fn update(&self, state: &mut State, _: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        // closes in on the player when the run stops
+        state.update_camera(real_delta_time, state.tuning.camera_zoom * 0.7);
+        None
+    }
+
+    fn render(&self, _: &World) -> Overlay {
+        Overlay::Text("Game Over".to_string(), "Press jump to try again".to_string())
+    }
+
+    fn menu(&self, ui: &mut Ui, world: &World, high_scores: &HighScores, last_rank: Option<usize>) -> Vec<GameStateChange> {
+        let score = &world.score;
+        ui.panel(|ui| {
+            ui.label(&format!("Score {}", score.score));
+            ui.label(&format!("Kills {}  best combo x{}", score.kills, score.best_combo));
+            if let Some(rank) = last_rank {
+                ui.label(&format!("New high score! #{}", rank + 1));
+            }
+            ui.label("High scores");
+            for (i, entry) in high_scores.entries.iter().take(5).enumerate() {
+                ui.label(&format!("{}. {}  level {}", i + 1, entry.score, entry.level));
+            }
+        });
+        vec![]
+    }
+}
+
+pub struct LevelCompleteState;
+
+impl GameStateBehavior for LevelCompleteState {
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        state.input_state.actions.pressed(Action::Jump).then_some(GameStateChange::NextLevel)
+    }
+
+    fn update(&self, state: &mut State, _: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        state.update_camera(real_delta_time, state.tuning.camera_zoom * 0.7);
+        None
+    }
+
+    fn render(&self, world: &World) -> Overlay {
+        Overlay::Text(format!("Level {} complete", world.level), "Press jump to continue".to_string())
+    }
+}
+
+pub struct EditorState;
+
+impl GameStateBehavior for EditorState {
+    // places and removes blocks under the cursor
+    fn handle_input(&self, state: &mut State) -> Option<GameStateChange> {
+        use stage::TileType::*;
+        let pos = state.input_state.mouse_position;
+        let rounded = Vector2::new((pos.x).floor() as i32, (pos.y).floor() as i32);
+        if state.input_state.mouse_pos_edge.contains(&MouseButton::Left) {
+            state.world.stage.values_mut().next().map(|stage| stage.set_tile(&rounded, Some(Dirt)));
+        }
+        if state.input_state.mouse_pos_edge.contains(&MouseButton::Right) {
+            state.world.stage.values_mut().next().map(|stage| stage.set_tile(&rounded, None));
+        }
+        state.world.debug_objects = vec![
+            ResolveInstance {
+                overlaps: 0,
+                color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+                position: Vector2::new(rounded.x as f32, rounded.y as f32) + Vector2::new(0.5, 0.5),
+                scale: Vector2::new(1.0, 1.0),
+            }
+        ];
+        None
+    }
+
+    fn update(&self, state: &mut State, _: f32, real_delta_time: f32) -> Option<GameStateChange> {
+        // the movement actions only fly the camera in the editor
+        state.camera_controller.update_camera(real_delta_time, &mut state.camera, &state.input_state.actions);
+        state.camera_controller.zoom_camera(&mut state.camera, state.input_state.mouse_wheel, Point2::from_vec(state.mouse_pos_view));
+        state.camera_follow.track(&state.camera);
+        None
+    }
+
+    fn render(&self, _: &World) -> Overlay {
+        Overlay::Debug
+    }
+
+    fn shows_hud(&self) -> bool {
+        false
+    }
+}
+
+pub struct GameStateStack {
+    stack: Vec<GameState>,
+}
+
+impl GameStateStack {
+    pub fn new(initial: GameState) -> Self {
+        Self {
+            stack: vec![initial],
+        }
+    }
+
+    pub fn current(&self) -> GameState {
+        *self.stack.last().expect("game state stack is never empty")
+    }
+
+    // what runs this frame, only the state on top of the stack gets input, updates and draws
+    pub fn top(&self) -> &'static dyn GameStateBehavior {
+        self.current().behavior()
+    }
+
+    // applies change if it makes sense from the current state, returns whether anything changed
+    pub fn apply(&mut self, change: GameStateChange) -> bool {
+        use GameState::*;
+        use GameStateChange as C;
+        match (self.current(), change) {
+            (Title, C::Start)
+            | (GameOver, C::Restart)
+            | (Playing, C::Restart)
+            | (Paused, C::Restart)
+            | (LevelComplete, C::NextLevel) => self.stack = vec![Playing],
+            (Playing, C::PlayerLose) => self.replace_top(GameOver),
+            (Playing, C::LevelComplete) => self.replace_top(LevelComplete),
+            (Playing, C::Pause) => self.stack.push(Paused),
+            (Paused, C::Resume)
+            | (Editor, C::ExitEditor) => { self.stack.pop(); },
+            (Paused, C::ToTitle)
+            | (GameOver, C::ToTitle) => self.stack = vec![Title],
+            (current, C::EnterEditor) if current != Editor => self.stack.push(Editor),
+            _ => return false,
+        }
+        true
+    }
+
+    fn replace_top(&mut self, state: GameState) {
+        self.stack.pop();
+        self.stack.push(state);
+    }
+}
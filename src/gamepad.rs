@@ -0,0 +1,135 @@
+use cgmath::{Vector2, InnerSpace, Zero};
+use gilrs::{Gilrs, EventType, Button, Axis};
+
+use crate::{InputState, input::{Bindings, Binding, GamepadButton}};
+
+// stick values below the inner deadzone read as 0, above the outer deadzone as full tilt
+const STICK_INNER_DEADZONE: f32 = 0.2;
+const STICK_OUTER_DEADZONE: f32 = 0.95;
+// how far the left stick has to be pushed to count as a direction press
+const STICK_PRESS_THRESHOLD: f32 = 0.5;
+
+// radial deadzone, rescaled so movement starts from 0 at the edge of the inner deadzone
+pub fn apply_deadzone(stick: Vector2<f32>, inner: f32, outer: f32) -> Vector2<f32> {
+    let magnitude = stick.magnitude();
+    if magnitude <= inner {
+        return Vector2::zero()
+    }
+    let scaled = f32::min(1.0, (magnitude - inner) / (outer - inner));
+    stick / magnitude * scaled
+}
+
+fn map_button(button: Button) -> Option<GamepadButton> {
+    use GamepadButton::*;
+    Some(match button {
+        Button::South => South,
+        Button::East => East,
+        Button::West => West,
+        Button::North => North,
+        Button::LeftTrigger => LeftShoulder,
+        Button::RightTrigger => RightShoulder,
+        Button::LeftTrigger2 => LeftTrigger,
+        Button::RightTrigger2 => RightTrigger,
+        Button::DPadUp => DPadUp,
+        Button::DPadDown => DPadDown,
+        Button::DPadLeft => DPadLeft,
+        Button::DPadRight => DPadRight,
+        Button::Start => Start,
+        Button::Select => Select,
+        _ => return None,
+    })
+}
+
+// the stick directions that count as pressed, in the same order as STICK_BUTTONS
+fn stick_presses(stick: Vector2<f32>) -> [bool; 4] {
+    [
+        stick.x < -STICK_PRESS_THRESHOLD,
+        stick.x > STICK_PRESS_THRESHOLD,
+        stick.y < -STICK_PRESS_THRESHOLD,
+        stick.y > STICK_PRESS_THRESHOLD,
+    ]
+}
+
+const STICK_BUTTONS: [GamepadButton; 4] = [
+    GamepadButton::StickLeft,
+    GamepadButton::StickRight,
+    GamepadButton::StickUp,
+    GamepadButton::StickDown,
+];
+
+pub struct Gamepad {
+    // None if gamepads aren't supported on this platform
+    gilrs: Option<Gilrs>,
+    left_stick_raw: Vector2<f32>,
+    right_stick_raw: Vector2<f32>,
+    stick_pressed: [bool; 4],
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                log::warn!("Gamepad support unavailable: {}", err);
+                None
+            }
+        };
+        Self {
+            gilrs,
+            left_stick_raw: Vector2::zero(),
+            right_stick_raw: Vector2::zero(),
+            stick_pressed: [false; 4],
+        }
+    }
+
+    // drains gamepad events into input_state, call once per frame before reading input
+    pub fn poll(&mut self, input_state: &mut InputState, bindings: &Bindings) {
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => if let Some(button) = map_button(button) {
+                    input_state.actions.handle(bindings, Binding::Gamepad(button), true);
+                },
+                EventType::ButtonReleased(button, _) => if let Some(button) = map_button(button) {
+                    input_state.actions.handle(bindings, Binding::Gamepad(button), false);
+                },
+                // gamepad y points up, the world's y points down
+                EventType::AxisChanged(Axis::LeftStickX, value, _) => self.left_stick_raw.x = value,
+                EventType::AxisChanged(Axis::LeftStickY, value, _) => self.left_stick_raw.y = -value,
+                EventType::AxisChanged(Axis::RightStickX, value, _) => self.right_stick_raw.x = value,
+                EventType::AxisChanged(Axis::RightStickY, value, _) => self.right_stick_raw.y = -value,
+                EventType::Disconnected => {
+                    self.left_stick_raw = Vector2::zero();
+                    self.right_stick_raw = Vector2::zero();
+                },
+                _ => (),
+            }
+        }
+
+        input_state.left_stick = apply_deadzone(self.left_stick_raw, STICK_INNER_DEADZONE, STICK_OUTER_DEADZONE);
+        input_state.right_stick = apply_deadzone(self.right_stick_raw, STICK_INNER_DEADZONE, STICK_OUTER_DEADZONE);
+
+        // the left stick presses the same actions as the d-pad by default
+        let pressed = stick_presses(input_state.left_stick);
+        for i in 0..STICK_BUTTONS.len() {
+            if pressed[i] != self.stick_pressed[i] {
+                input_state.actions.handle(bindings, Binding::Gamepad(STICK_BUTTONS[i]), pressed[i]);
+            }
+        }
+        self.stick_pressed = pressed;
+
+        // aim with the right stick until the mouse moves again
+        if !input_state.right_stick.is_zero() {
+            input_state.aim_stick = Some(input_state.right_stick.normalize());
+        }
+    }
+}
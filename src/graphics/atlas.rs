@@ -0,0 +1,102 @@
+// packs sprites into one texture at load time, so everything drawn from it can share a single draw call
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use cgmath::Vector2;
+use image::RgbaImage;
+
+use crate::assets::Handle;
+
+use super::{packing::{self, RectInfo}, textured::UvRect, texture::Texture};
+
+// empty pixels around each sprite, filled by extending its edges so filtering never picks up a neighbor
+const PADDING: u32 = 1;
+
+// sizes tried are 2^6 to 2^12 pixels square
+const MIN_LOG_SIZE: u32 = 6;
+const MAX_LOG_SIZE: u32 = 12;
+
+pub struct Atlas {
+    pub texture: Handle<Texture>,
+    pub filter: wgpu::FilterMode,
+    paths: Vec<String>,
+    regions: HashMap<String, UvRect>,
+    // in pixels
+    size: Vector2<f32>,
+}
+
+impl Atlas {
+    pub fn new(texture: Handle<Texture>, filter: wgpu::FilterMode, paths: Vec<String>, image: &RgbaImage, regions: HashMap<String, UvRect>) -> Self {
+        let size = Vector2::new(image.width() as f32, image.height() as f32);
+        Self { texture, filter, paths, regions, size }
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.regions.contains_key(path)
+    }
+
+    // where the sprite loaded from path is, or the whole atlas if it isn't in it
+    pub fn region(&self, path: &str) -> UvRect {
+        self.regions.get(path).copied().unwrap_or_default()
+    }
+
+    // part of the sprite loaded from path, given in pixels of that sprite
+    pub fn sub_region(&self, path: &str, x: u32, y: u32, width: u32, height: u32) -> UvRect {
+        let region = self.region(path);
+        UvRect {
+            offset: region.offset + Vector2::new(x as f32 / self.size.x, y as f32 / self.size.y),
+            size: Vector2::new(width as f32 / self.size.x, height as f32 / self.size.y),
+        }
+    }
+
+    pub fn set_regions(&mut self, image: &RgbaImage, regions: HashMap<String, UvRect>) {
+        self.size = Vector2::new(image.width() as f32, image.height() as f32);
+        self.regions = regions;
+    }
+}
+
+// copies the images into one, returning it and where each image ended up
+pub fn pack_images(images: &[(String, RgbaImage)]) -> Result<(RgbaImage, HashMap<String, UvRect>)> {
+    let rects: Vec<RectInfo<usize>> = images.iter().enumerate().map(|(i, (_, image))| RectInfo {
+        id: i,
+        width: image.width() + PADDING * 2,
+        height: image.height() + PADDING * 2,
+    }).collect();
+    let packing = packing::pack(&rects, MIN_LOG_SIZE, MAX_LOG_SIZE)
+        .ok_or_else(|| anyhow!("sprites don't fit in a {0}x{0} atlas", 1 << MAX_LOG_SIZE))?;
+    debug_assert!(packing.is_valid(&rects));
+
+    let mut atlas = RgbaImage::new(packing.width(), packing.height());
+    let atlas_size = Vector2::new(packing.width() as f32, packing.height() as f32);
+    let mut regions = HashMap::new();
+    for (i, (path, image)) in images.iter().enumerate() {
+        let pos = packing.get_pos(i).unwrap();
+        let (left, top) = (pos.x + PADDING, pos.y + PADDING);
+        blit_extruded(&mut atlas, image, left, top);
+        regions.insert(path.clone(), UvRect {
+            offset: Vector2::new(left as f32 / atlas_size.x, top as f32 / atlas_size.y),
+            size: Vector2::new(image.width() as f32 / atlas_size.x, image.height() as f32 / atlas_size.y),
+        });
+    }
+    Ok((atlas, regions))
+}
+
+// copies image to (left, top) and repeats its edge pixels into the padding around it
+fn blit_extruded(atlas: &mut RgbaImage, image: &RgbaImage, left: u32, top: u32) {
+    if image.width() == 0 || image.height() == 0 {
+        return
+    }
+    let pad = PADDING as i64;
+    for y in -pad..image.height() as i64 + pad {
+        for x in -pad..image.width() as i64 + pad {
+            let source_x = x.clamp(0, image.width() as i64 - 1) as u32;
+            let source_y = y.clamp(0, image.height() as i64 - 1) as u32;
+            let pixel = *image.get_pixel(source_x, source_y);
+            atlas.put_pixel((left as i64 + x) as u32, (top as i64 + y) as u32, pixel);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// 47 tile blob autotiling: every tile picks one of 47 variants from which of its 8 neighbors are solid.
+// a corner neighbor only matters when both edges next to it are solid, which leaves 47 distinct cases
+use image::{Rgba, RgbaImage};
+
+pub const NORTH: u8 = 1;
+pub const NORTH_EAST: u8 = 2;
+pub const EAST: u8 = 4;
+pub const SOUTH_EAST: u8 = 8;
+pub const SOUTH: u8 = 16;
+pub const SOUTH_WEST: u8 = 32;
+pub const WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+pub const VARIANTS: usize = 47;
+
+// offsets of the neighbors in bit order, y points down so north is -1
+const NEIGHBORS: [(i32, i32, u8); 8] = [
+    (0, -1, NORTH),
+    (1, -1, NORTH_EAST),
+    (1, 0, EAST),
+    (1, 1, SOUTH_EAST),
+    (0, 1, SOUTH),
+    (-1, 1, SOUTH_WEST),
+    (-1, 0, WEST),
+    (-1, -1, NORTH_WEST),
+];
+
+// each corner and the two edges it sits between
+const CORNERS: [(u8, u8, u8); 4] = [
+    (NORTH_EAST, NORTH, EAST),
+    (SOUTH_EAST, SOUTH, EAST),
+    (SOUTH_WEST, SOUTH, WEST),
+    (NORTH_WEST, NORTH, WEST),
+];
+
+// the bits of the neighbors that are solid
+pub fn neighbor_mask(solid: impl Fn(i32, i32) -> bool) -> u8 {
+    NEIGHBORS.iter()
+        .filter(|(dx, dy, _)| solid(*dx, *dy))
+        .fold(0, |mask, (_, _, bit)| mask | bit)
+}
+
+// drops corners that don't have both of their edges, they look the same either way
+pub const fn reduce(mask: u8) -> u8 {
+    let mut reduced = mask;
+    let mut i = 0;
+    while i < CORNERS.len() {
+        let (corner, a, b) = CORNERS[i];
+        if mask & a == 0 || mask & b == 0 {
+            reduced &= !corner;
+        }
+        i += 1;
+    }
+    reduced
+}
+
+const fn blob_masks() -> [u8; VARIANTS] {
+    let mut masks = [0; VARIANTS];
+    let mut count = 0;
+    let mut mask = 0;
+    // counting up means every reduced mask is found in ascending order
+    while mask < 256 {
+        if reduce(mask as u8) == mask as u8 {
+            masks[count] = mask as u8;
+            count += 1;
+        }
+        mask += 1;
+    }
+    masks
+}
+
+// every reduced mask in ascending order, a mask's position is its variant
+pub const BLOB_MASKS: [u8; VARIANTS] = blob_masks();
+
+// which of the 47 variants a tile with these solid neighbors uses
+pub fn variant(mask: u8) -> usize {
+    BLOB_MASKS.binary_search(&reduce(mask))
+        .expect("every reduced mask is one of the blob masks")
+}
+
+// draws the variant for mask from a plain tile, shading the edges that face open space
+// and the inside corners where only the diagonal neighbor is open
+pub fn make_variant(base: &RgbaImage, mask: u8) -> RgbaImage {
+    let (width, height) = base.dimensions();
+    let border = u32::max(1, width / 16);
+    let mut image = base.clone();
+    let open = |bit: u8| mask & bit == 0;
+    for y in 0..height {
+        for x in 0..width {
+            let (left, right) = (x < border, x >= width - border);
+            let (top, bottom) = (y < border, y >= height - border);
+            let edge = (top && open(NORTH)) || (bottom && open(SOUTH))
+                || (left && open(WEST)) || (right && open(EAST));
+            let inner_corner = (top && right && open(NORTH_EAST))
+                || (bottom && right && open(SOUTH_EAST))
+                || (bottom && left && open(SOUTH_WEST))
+                || (top && left && open(NORTH_WEST));
+            if edge || inner_corner {
+                let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+                image.put_pixel(x, y, Rgba([r / 2, g / 2, b / 2, a]));
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_masks_are_47_sorted_unique() {
+        assert_eq!(BLOB_MASKS.len(), 47);
+        assert!(BLOB_MASKS.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(BLOB_MASKS.iter().all(|mask| reduce(*mask) == *mask));
+    }
+
+    #[test]
+    fn reduce_keeps_corners_only_between_two_edges() {
+        assert_eq!(reduce(NORTH_EAST), 0);
+        assert_eq!(reduce(NORTH_EAST | NORTH), NORTH);
+        assert_eq!(reduce(NORTH_EAST | EAST), EAST);
+        assert_eq!(reduce(NORTH_EAST | NORTH | EAST), NORTH_EAST | NORTH | EAST);
+        assert_eq!(reduce(SOUTH_WEST | SOUTH | WEST | NORTH_WEST), SOUTH_WEST | SOUTH | WEST);
+        assert_eq!(reduce(0xff), 0xff);
+    }
+
+    #[test]
+    fn neighbor_mask_uses_y_down() {
+        let cases = [
+            ((0, -1), NORTH),
+            ((1, -1), NORTH_EAST),
+            ((1, 0), EAST),
+            ((1, 1), SOUTH_EAST),
+            ((0, 1), SOUTH),
+            ((-1, 1), SOUTH_WEST),
+            ((-1, 0), WEST),
+            ((-1, -1), NORTH_WEST),
+        ];
+        for ((x, y), bit) in cases {
+            assert_eq!(neighbor_mask(|dx, dy| (dx, dy) == (x, y)), bit, "offset ({}, {})", x, y);
+        }
+        assert_eq!(neighbor_mask(|_, _| false), 0);
+        assert_eq!(neighbor_mask(|_, _| true), 0xff);
+    }
+
+    #[test]
+    fn variants_of_common_shapes() {
+        // a lone tile is the first variant and the middle of a solid block the last
+        assert_eq!(variant(0), 0);
+        assert_eq!(variant(0xff), VARIANTS - 1);
+        // an inside corner, every neighbor but the north east diagonal
+        let inside_corner = 0xff & !NORTH_EAST;
+        assert_eq!(BLOB_MASKS[variant(inside_corner)], inside_corner);
+        assert_eq!(variant(inside_corner), VARIANTS - 2);
+        // corners without both edges don't change the variant
+        assert_eq!(variant(NORTH | NORTH_EAST), variant(NORTH));
+    }
+}
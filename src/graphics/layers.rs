@@ -0,0 +1,90 @@
+// world sprites are drawn back to front by layer, and within a layer by a sort key.
+// kept apart from the renderer so the order can be worked out without a gpu
+use super::textured::{self, Instance};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Layer {
+    // decorations behind the level
+    Background,
+    Tiles,
+    Projectiles,
+    Characters,
+    // decorations drawn over the characters
+    Foreground,
+}
+
+pub struct LayeredInstance<T> {
+    pub layer: Layer,
+    // lower draws first, e.g. the y of a character's feet so characters further down stand in front
+    pub key: f32,
+    pub texture: T,
+    pub instance: Instance,
+}
+
+impl<T> LayeredInstance<T> {
+    pub fn new(layer: Layer, key: f32, texture: T, instance: Instance) -> Self {
+        Self { layer, key, texture, instance }
+    }
+}
+
+// stable, so instances with the same layer and key keep the order they were added in
+pub fn sort<T>(instances: &mut [LayeredInstance<T>]) {
+    instances.sort_by(|a, b| a.layer.cmp(&b.layer).then(a.key.total_cmp(&b.key)));
+}
+
+// sorts the instances then groups neighbors with the same texture, ready to be drawn in order
+pub fn into_batches<T: PartialEq>(mut instances: Vec<LayeredInstance<T>>) -> Vec<(Vec<Instance>, T)> {
+    sort(&mut instances);
+    textured::merge_neighbors(instances.into_iter().map(|layered| (vec![layered.instance], layered.texture)), T::eq)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector2;
+
+    use super::*;
+
+    // an instance whose x records the order it was added in
+    fn at(layer: Layer, key: f32, texture: u32, order: f32) -> LayeredInstance<u32> {
+        LayeredInstance::new(layer, key, texture, Instance { position: Vector2::new(order, 0.0), ..Default::default() })
+    }
+
+    fn order(instances: &[LayeredInstance<u32>]) -> Vec<f32> {
+        instances.iter().map(|instance| instance.instance.position.x).collect()
+    }
+
+    #[test]
+    fn layer_beats_key() {
+        let mut instances = vec![
+            at(Layer::Foreground, -100.0, 0, 0.0),
+            at(Layer::Characters, 5.0, 0, 1.0),
+            at(Layer::Characters, 1.0, 0, 2.0),
+            at(Layer::Background, 100.0, 0, 3.0),
+        ];
+        sort(&mut instances);
+        assert_eq!(order(&instances), vec![3.0, 2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn equal_layer_and_key_keep_their_order() {
+        let mut instances: Vec<_> = (0..10).map(|i| at(Layer::Projectiles, 0.0, i % 3, i as f32)).collect();
+        sort(&mut instances);
+        assert_eq!(order(&instances), (0..10).map(|i| i as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn batches_only_merge_neighbors_with_the_same_texture() {
+        let instances = vec![
+            at(Layer::Background, 0.0, 1, 0.0),
+            at(Layer::Background, 1.0, 1, 1.0),
+            at(Layer::Tiles, 0.0, 2, 2.0),
+            at(Layer::Characters, 0.0, 1, 3.0),
+            at(Layer::Characters, 1.0, 1, 4.0),
+        ];
+        let batches: Vec<(Vec<f32>, u32)> = into_batches(instances).into_iter()
+            .map(|(instances, texture)| (instances.iter().map(|instance| instance.position.x).collect(), texture))
+            .collect();
+        // texture 1 shows up twice since texture 2 is drawn between
+        assert_eq!(batches, vec![(vec![0.0, 1.0], 1), (vec![2.0], 2), (vec![3.0, 4.0], 1)]);
+    }
+}
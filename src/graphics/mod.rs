@@ -1,11 +1,18 @@
-use cgmath::{Vector2, Vector4};
+use anyhow::Context;
+use cgmath::{Vector2, Vector4, EuclideanSpace};
 
-use crate::{graphics::texture::Texture, camera::Camera, world::{World, physics::Physics, projectile::ProjectileType}, chatbox::Chatbox};
-use self::{textured::{TextureRenderer, Instance}, text::{Font, FontRenderer, make_font_infos, default_characters}};
+use crate::{assets::{Assets, Handle}, graphics::atlas::Atlas, animation::{Animations, Animator}, camera::Camera, world::{World, player, basic_enemy, jumping_enemy, physics::Physics, projectile::ProjectileEffect}, chatbox::Chatbox, game_state::{GameStateStack, Overlay}, ui::{self, DrawCommand}, camera::UICamera};
+use self::{layers::{Layer, LayeredInstance}, parallax::Backgrounds, tilemap::Tilemap, texture::Texture, textured::{TextureRenderer, Instance, UvRect}, text::{Font, FontRenderer, BaseFontInfoContainer, FontInfoContainer}};
 
 pub mod textured;
 pub mod text;
 pub mod texture;
+pub mod packing;
+pub mod atlas;
+pub mod layers;
+pub mod autotile;
+pub mod tilemap;
+pub mod parallax;
 
 pub struct RenderPrereq<'a> {
     pub device: &'a mut wgpu::Device,
@@ -17,21 +24,42 @@ pub struct RenderPrereq<'a> {
 pub struct RenderEngine {
     pub texture_renderer: TextureRenderer,
     pub ui_texture_renderer: TextureRenderer,
-    pub font: Font,
+    pub font: Handle<Font>,
+    // every sprite drawn in the world, so the world is one draw call
+    pub sprites: Handle<Atlas>,
+    pub animations: Animations,
+    pub backgrounds: Backgrounds,
+    tileset: Handle<Texture>,
+    tilemap: Tilemap,
     font_renderer: FontRenderer,
+    // the surface format, which the offscreen target has to match to use the same pipelines
+    format: wgpu::TextureFormat,
+    // what the world is drawn into in pixel perfect mode, made the first time it's needed
+    pixel_target: Option<Texture>,
+}
+
+// the tile every autotile variant is drawn from
+pub const TILE: &str = "tile_glass_holy.png";
 
-    pub diffuse_texture: Texture,
-    pub solid_texture: Texture,
-    pub background_texture: Texture,
-    pub basic_texture: Texture,
-    pub player_texture: Texture,
-    pub spearman_texture: Texture,
-    pub tile_texture: Texture,
-    pub tile_stained_glass: Texture,
-    pub red_ball_texture: Texture,
-    pub green_ball_texture: Texture,
+// every autotile variant of TILE in one image, and where each variant is
+fn load_tileset(assets: &mut Assets) -> anyhow::Result<(image::DynamicImage, Vec<UvRect>)> {
+    let (tileset, variants) = assets.load_data(TILE)
+        .and_then(|bytes| Ok(image::load_from_memory(&bytes)?.to_rgba8()))
+        .and_then(|base| Tilemap::make_tileset(&base))
+        .with_context(|| format!("couldn't make the tileset from {}", TILE))?;
+    Ok((image::DynamicImage::ImageRgba8(tileset), variants))
 }
 
+// sprites drawn by the engine, loaded up front so a missing one is reported at startup
+const SPRITES: &[&str] = &[
+    "basic.png",
+    "player.png",
+    "spearman.png",
+    "tile_stained_glass.png",
+    "red_ball.png",
+    "green_ball.png",
+];
+
 #[derive(Clone)]
 pub struct ResolveInstance {
     pub position: cgmath::Vector2<f32>,
@@ -45,227 +73,285 @@ impl Into<Instance> for ResolveInstance {
         Instance {
             position: self.position,
             scale: self.scale,
-            color: self.color
+            color: self.color,
+            ..Default::default()
         }
     }
 }
 
 impl RenderEngine {
-    pub fn init(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration) -> RenderEngine {
-        // load a texture - happy tree
-        let diffuse_bytes = include_bytes!("happy-tree.png");
-        let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-        let diffuse_texture =
-            Texture::from_image(&device, &queue, &diffuse_image, "happy-tree", wgpu::FilterMode::Linear).unwrap();
-        let solid_texture =
-            Texture::blank_texture(&device, &queue, "blank").unwrap();
-        let mut texture_renderer = TextureRenderer::init(device, queue, config);
-        texture_renderer.add_texture(device, [&diffuse_texture, &solid_texture].into_iter());
-        let mut ui_texture_renderer = TextureRenderer::init(device, queue, config);
-        ui_texture_renderer.add_texture(device, [&diffuse_texture, &solid_texture].into_iter());
-        let font_info = make_font_infos(
-            include_bytes!("arial.ttf"), &[48.0], default_characters().iter(), None, "arial".to_string()).unwrap();
-        let font = Font::make_from_info(device, queue, &font_info[0], wgpu::FilterMode::Linear).unwrap();
-        let mut font_renderer = FontRenderer::new(device, queue, config).unwrap();
-        font_renderer.register_font(device, &font);
-
-        let load_image = |bytes, s| {
-            let diffuse_bytes = bytes;
-            let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-            let diffuse_texture =
-                Texture::from_image(&device, &queue, &diffuse_image, s, wgpu::FilterMode::Nearest).unwrap();
-            diffuse_texture
-        };
+    pub fn init(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, assets: &mut Assets) -> anyhow::Result<RenderEngine> {
+        let sprites = assets.load_atlas(device, queue, "sprites", SPRITES, wgpu::FilterMode::Nearest)?;
+        let (tileset, variants) = load_tileset(assets)?;
+        let tileset = Texture::from_image(device, queue, &tileset, "tileset", wgpu::FilterMode::Nearest)?;
+        let tileset = assets.add_texture(tileset);
+        let font = assets.load_font(device, queue, "arial.ttf", 48.0)?;
+        let font_renderer = FontRenderer::new(device, queue, config).map_err(|err| anyhow::anyhow!(err))?;
 
-        let background_texture = load_image(include_bytes!("background.png"), "background.png");
-        let basic_texture = load_image(include_bytes!("basic.png"), "basic.png");
-        let player_texture = load_image(include_bytes!("player.png"), "player.png");
-        let spearman_texture = load_image(include_bytes!("spearman.png"), "spearman.png");
-        let tile_texture = load_image(include_bytes!("tile_glass_holy.png"), "tile_glass_holy.png");
-        let tile_stained_glass = load_image(include_bytes!("tile_stained_glass.png"), "tile_stained_glass.png");
-        let red_ball_texture = load_image(include_bytes!("red_ball.png"), "red_ball.png");
-        let green_ball_texture = load_image(include_bytes!("green_ball.png"), "green_ball.png");
-        texture_renderer.add_texture(
-            device,
-            vec![
-                &background_texture,
-                &basic_texture,
-                &player_texture,
-                &spearman_texture,
-                &tile_texture,
-                &tile_stained_glass,
-                &red_ball_texture,
-                &green_ball_texture,
-            ].into_iter());
-        ui_texture_renderer.add_texture(
-            device,
-            vec![&background_texture].into_iter());
-
-        Self {
-            texture_renderer,
-            diffuse_texture,
-            solid_texture,
+        let mut engine = Self {
+            texture_renderer: TextureRenderer::init(device, queue, config),
+            ui_texture_renderer: TextureRenderer::init(device, queue, config),
             font,
+            sprites,
+            animations: Animations::load(assets),
+            backgrounds: Backgrounds::load(device, queue, assets),
+            tileset,
+            tilemap: Tilemap::new(variants),
             font_renderer,
-            ui_texture_renderer,
-            background_texture,
-            basic_texture,
-            player_texture,
-            spearman_texture,
-            tile_texture,
-            tile_stained_glass,
-            red_ball_texture,
-            green_ball_texture,
+            format: config.format,
+            pixel_target: None,
+        };
+        engine.register_assets(device, assets);
+        Ok(engine)
+    }
+
+    // makes bind groups for the textures and fonts in assets, called again whenever assets are loaded or reloaded
+    pub fn register_assets(&mut self, device: &wgpu::Device, assets: &Assets) {
+        self.texture_renderer.add_texture(device, assets.textures());
+        self.ui_texture_renderer.add_texture(device, assets.textures());
+        for font in assets.fonts() {
+            self.font_renderer.register_font(device, font);
+        }
+    }
+
+    // makes the tileset again after TILE changed, the tilemap starts over since the variants may have moved
+    pub fn reload_tileset(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, assets: &mut Assets) -> anyhow::Result<()> {
+        let (image, variants) = load_tileset(assets)?;
+        // a tileset that changed size can't be written in place
+        if assets.texture(self.tileset).write_image(queue, &image).is_err() {
+            let texture = Texture::from_image(device, queue, &image, "tileset", wgpu::FilterMode::Nearest)?;
+            assets.replace_texture(self.tileset, texture);
+            self.forget_texture("tileset");
+        }
+        self.tilemap = Tilemap::new(variants);
+        Ok(())
+    }
+
+    // drops the bind groups of a texture or font that was replaced, so register_assets makes them again
+    pub fn forget_texture(&mut self, id: &str) {
+        self.texture_renderer.remove_texture(id);
+        self.ui_texture_renderer.remove_texture(id);
+        self.font_renderer.unregister_font(id);
+    }
+
+    fn prepare_pixel_target(&mut self, device: &wgpu::Device, camera: &Camera) {
+        if camera.pixel_perfect && self.pixel_target.is_none() {
+            let target = Texture::render_target(device, Camera::VIRTUAL_SIZE, self.format, "pixel_target");
+            self.ui_texture_renderer.add_texture(device, std::iter::once(&target));
+            self.pixel_target = Some(target);
         }
     }
 
-    pub fn render(&mut self, render: RenderPrereq, chatbox: &Chatbox, world: &World) -> Result<(), wgpu::SurfaceError> {
+    pub fn render(&mut self, render: RenderPrereq, assets: &Assets, chatbox: &Chatbox, world: &World, game_states: &GameStateStack, ui_commands: &[DrawCommand]) -> Result<(), wgpu::SurfaceError> {
+        self.tilemap.update(render.device, &self.texture_renderer, world.stage.values());
+        self.prepare_pixel_target(render.device, render.camera);
+        let font = assets.font(self.font);
+        let solid_texture = assets.texture(assets.blank);
+        let sprites = assets.atlas(self.sprites);
+        let sprite = |path| sprites.region(path);
+        let animated = |animator, facing_left, fallback| animation_frame(&self.animations, sprites, animator, facing_left, fallback);
+
+        // in pixel perfect mode the world is drawn small into its own target, then scaled up onto the window
+        let pixel_target = self.pixel_target.as_ref().filter(|_| render.camera.pixel_perfect);
+
         let output = render.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let mut encoder = render.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
+        self.texture_renderer.reset();
+        self.font_renderer.reset();
+        self.ui_texture_renderer.reset();
 
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(
-                                wgpu::Color {
-                                    r: 0.1,
-                                    g: 0.2,
-                                    b: 0.3,
-                                    a: 1.0,
-                                }
-                            ),
-                            store: true,
-                        },
+            let clear = wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            };
+            let world_view = pixel_target.map_or(&view, |target| &target.view);
+            let mut render_pass = begin_render_pass(&mut encoder, world_view, wgpu::LoadOp::Clear(clear));
+
+            // render the background layers of the level, each following the camera by its own amount
+            let camera = render.camera;
+            let backgrounds = self.backgrounds.layers(world.level).iter()
+                .flat_map(|layer| self.backgrounds.texture(layer).map(|handle| (layer, assets.texture(handle))))
+                .map(|(layer, texture)| {
+                    let (width, height) = texture.dimensions;
+                    let size = Vector2::new(layer.height * width as f32 / height as f32, layer.height);
+                    (layer.instances(size, camera.view_center().to_vec(), camera.half_extent(), world.elapsed), texture)
+                })
+                .collect();
+            self.texture_renderer.render(render.queue, &mut render_pass, camera, backgrounds)?;
+
+            // all world sprites come from the atlas, so the layers only split batches where they have to
+            let atlas = sprites.texture;
+            let mut instances = vec![];
+            for decoration in world.stage.values().flat_map(|stage| &stage.decorations) {
+                let layer = if decoration.foreground { Layer::Foreground } else { Layer::Background };
+                instances.push(LayeredInstance::new(layer, 0.0, atlas, Instance {
+                    position: decoration.position,
+                    scale: decoration.size,
+                    uv: sprite(decoration.sprite),
+                    ..Default::default()
+                }));
+            }
+            for (effect, path) in [
+                (ProjectileEffect::Knockback, "green_ball.png"),
+                (ProjectileEffect::Slow, "red_ball.png"),
+            ] {
+                instances.extend(world.projectiles.iter()
+                                .filter(|projectile| projectile.def.effect == effect)
+                                .flat_map(|projectile| projectile.get_physics())
+                                .map(|(_, phys)|
+                    LayeredInstance::new(Layer::Projectiles, 0.0, atlas, Instance {
+                        position: phys.bounding_box.center,
+                        scale: phys.bounding_box.get_scale(),
+                        // point along the direction of travel
+                        rotation: phys.velocity.y.atan2(phys.velocity.x),
+                        uv: sprite(path),
+                        ..Default::default()
                     })
-                ],
-                depth_stencil_attachment: None,
-            });
-            self.texture_renderer.reset();
-            self.font_renderer.reset();
-            self.ui_texture_renderer.reset();
+                ));
+            }
+            // characters further down the screen stand in front
+            let (uv, flip_x) = animated(&world.player.animator, world.player.direction == player::Direction::Left, "player.png");
+            instances.push(LayeredInstance::new(Layer::Characters, world.player.physics.bounding_box.get_y_max(), atlas, Instance {
+                position: world.player.physics.bounding_box.center,
+                scale: Vector2::new(world.player.physics.bounding_box.get_scale().y, world.player.physics.bounding_box.get_scale().y),
+                uv,
+                flip_x,
+                ..Default::default()
+            }));
+            // enemies stand on the bottom of their bounding boxes
+            instances.extend(world.basic_enemies.iter().map(|enemy| {
+                let bounding_box = &enemy.physics.bounding_box;
+                let (uv, flip_x) = animated(&enemy.animator, enemy.direction == basic_enemy::Direction::Left, "basic.png");
+                LayeredInstance::new(Layer::Characters, bounding_box.get_y_max(), atlas, Instance {
+                    position: Vector2::new(bounding_box.center.x, bounding_box.get_y_max()),
+                    scale: Vector2::new(bounding_box.height, bounding_box.height) * 1.25,
+                    uv,
+                    flip_x,
+                    pivot: Instance::PIVOT_BOTTOM,
+                    ..Default::default()
+                })
+            }));
+            instances.extend(world.jumping_enemies.iter().map(|enemy| {
+                let bounding_box = &enemy.physics.bounding_box;
+                let (uv, flip_x) = animated(&enemy.animator, enemy.direction == jumping_enemy::Direction::Left, "spearman.png");
+                LayeredInstance::new(Layer::Characters, bounding_box.get_y_max(), atlas, Instance {
+                    position: Vector2::new(bounding_box.center.x, bounding_box.get_y_max()),
+                    scale: Vector2::new(bounding_box.height, bounding_box.height),
+                    uv,
+                    flip_x,
+                    pivot: Instance::PIVOT_BOTTOM,
+                    ..Default::default()
+                })
+            }));
+            // the tilemap keeps its own buffers, so it's drawn between the layers behind it and the rest
+            let (behind, in_front): (Vec<_>, Vec<_>) = instances.into_iter().partition(|instance| instance.layer < Layer::Tiles);
+            let batches = |instances| layers::into_batches(instances).into_iter()
+                .map(|(instances, texture)| (instances, assets.texture(texture)))
+                .collect();
+            self.texture_renderer.render(render.queue, &mut render_pass, render.camera, batches(behind))?;
+            self.texture_renderer.render_static(render.queue, &mut render_pass, render.camera,
+                &self.tilemap.chunks(), assets.texture(self.tileset))?;
+            self.texture_renderer.render(render.queue, &mut render_pass, render.camera, batches(in_front))?;
+
+        }
 
+        {
+            // the letterbox bars are cleared to black around the scaled up world
+            let load = match pixel_target {
+                Some(_) => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                None => wgpu::LoadOp::Load,
+            };
+            let mut render_pass = begin_render_pass(&mut encoder, &view, load);
             let ui_camera = render.camera.get_ui_camera();
 
-            // render instructions go here
-            // render background
-            self.ui_texture_renderer.render(
-                render.queue,
-                &mut render_pass,
-                &ui_camera,
-                vec![
-                    (vec![Instance {
-                        position: Vector2::new(render.camera.window_size.x as f32 / 2.0,
-                                               render.camera.window_size.y as f32 / 2.0),
-                        scale: Vector2::new(render.camera.window_size.x as f32 / 1.0,
-                                               render.camera.window_size.y as f32 / 1.0),
-                        color: Vector4::new(1.0, 1.0, 1.0, 1.0),
-                    }], &self.background_texture)
-                ]
-            )?;
+            if let Some(target) = pixel_target {
+                let scaled = render.camera.world_to_view_scale(render.camera.half_extent() * 2.0);
+                self.ui_texture_renderer.render(
+                    render.queue,
+                    &mut render_pass,
+                    &ui_camera,
+                    vec![
+                        (vec![Instance {
+                            position: render.camera.letterbox_offset() + scaled / 2.0,
+                            scale: scaled,
+                            ..Default::default()
+                        }], target)
+                    ]
+                )?;
+            }
 
-            let mut instances = vec![(
-                vec![Instance {
-                    position: world.player.physics.bounding_box.center,
-                    scale: Vector2::new(world.player.physics.bounding_box.get_scale().y, world.player.physics.bounding_box.get_scale().y),
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }], &self.player_texture),
-            ];
-            instances.push((world.stage.iter().map(|(_, stage)| stage.get_physics()).flatten().map(|(_, phys)|
-                Instance {
-                    position: phys.bounding_box.center,
-                    scale: phys.bounding_box.get_scale(),
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }).collect(), &self.tile_texture)
-            );
-            instances.push((world.projectiles.iter()
-                            .flat_map(|projectile|
-                                      if projectile.typ == ProjectileType::Basic {
-                                          Some(projectile.get_physics())
-                                      } else { None} )
-                            .flatten().map(|(_, phys)|
-                Instance {
-                    position: phys.bounding_box.center,
-                    scale: phys.bounding_box.get_scale(),
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }).collect(), &self.green_ball_texture
-            ));
-            instances.push((world.projectiles.iter()
-                            .flat_map(|projectile|
-                                      if projectile.typ == ProjectileType::Slowing {
-                                          Some(projectile.get_physics())
-                                      } else { None} )
-                            .flatten().map(|(_, phys)|
-                Instance {
-                    position: phys.bounding_box.center,
-                    scale: phys.bounding_box.get_scale(),
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }).collect(), &self.red_ball_texture
-            ));
-            instances.push((world.basic_enemies.iter().map(|enemy| enemy.get_physics()).flatten().map(|(_, phys)|
-                Instance {
-                    position: phys.bounding_box.center + Vector2::new(0.0, -0.15),
-                    scale: Vector2::new(phys.bounding_box.height, phys.bounding_box.height) * 1.25,
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }).collect(), &self.basic_texture)
-            );
-            instances.push((world.jumping_enemies.iter().map(|enemy| enemy.get_physics()).flatten().map(|(_, phys)|
-                Instance {
-                    position: phys.bounding_box.center + Vector2::new(0.0, -0.15),
-                    scale: Vector2::new(phys.bounding_box.height, phys.bounding_box.height),
-                    color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
-                }).collect(), &self.spearman_texture)
-            );
-            // instances.extend(world.jumping_enemies.iter().map(|enemy| enemy.get_physics()).flatten().map(|(_, phys)|
-            //     Instance {
-            //         position: phys.bounding_box.center,
-            //         scale: phys.bounding_box.get_scale(),
-            //         color: cgmath::Vector4::new(0.0, 0.8, 0.0, 1.0),
-            //     })
-            // );
-            self.texture_renderer.render(render.queue, &mut render_pass, render.camera,
-                instances)?;
-
-            let text = format!("{:?}\n{}", world.player.aerial_state, world.player.physics.velocity.y);
-            let mut font_instances = vec![(text.clone(),
-                    cgmath::Vector2::new(0.0, 38.0),
-                    cgmath::Vector4::new(1.0, 0.5, 1.0, 1.0))];
-            font_instances.extend(world.debug_objects.iter().map(|i| {
-                (
-                    format!("{}", i.overlaps),
-                    render.camera.world_to_view_pos(cgmath::Point2::new(0.0, 0.0) + i.position),
-                    cgmath::Vector4::new(i.color.x, i.color.y, i.color.z, 0.8)
-                )
-            }));
-            self.font_renderer.render(&self.font, render.queue, &mut render_pass, &ui_camera,
-                &font_instances)?;
+            let overlay = game_states.top().render(world);
+
+            // player state debug text, only shown in the editor
+            if let Overlay::Debug = overlay {
+                let text = format!("{:?}\n{}", world.player.aerial_state, world.player.physics.velocity.y);
+                let mut font_instances = vec![(text.clone(),
+                        cgmath::Vector2::new(0.0, 38.0),
+                        cgmath::Vector4::new(1.0, 0.5, 1.0, 1.0))];
+                font_instances.extend(world.debug_objects.iter().map(|i| {
+                    (
+                        format!("{}", i.overlaps),
+                        render.camera.world_to_view_pos(cgmath::Point2::new(0.0, 0.0) + i.position),
+                        cgmath::Vector4::new(i.color.x, i.color.y, i.color.z, 0.8)
+                    )
+                }));
+                self.font_renderer.render(font, render.queue, &mut render_pass, &ui_camera,
+                    &font_instances)?;
             
-            // copying code above for the purpose of horizontal info
-            let text = format!("{:?}\n{}", world.player.horizontal_state, world.player.physics.velocity.x);
-            let font_instances = vec![(text.clone(),
-                    cgmath::Vector2::new(400.0, 38.0),
-                    cgmath::Vector4::new(1.0, 0.5, 1.0, 1.0))];
-            // font_instances.extend(world.debug_objects.iter().map(|i| {
-            //     (
-            //         format!("{}", i.overlaps),
-            //         render.camera.world_to_view_pos(cgmath::Point2::new(0.0, 0.0) + i.position),
-            //         cgmath::Vector4::new(i.color.x, i.color.y, i.color.z, 0.8)
-            //     )
-            // }));
-            self.font_renderer.render(&self.font, render.queue, &mut render_pass, &ui_camera,
-                &font_instances)?;
+                // copying code above for the purpose of horizontal info
+                let text = format!("{:?}\n{}", world.player.horizontal_state, world.player.physics.velocity.x);
+                let font_instances = vec![(text.clone(),
+                        cgmath::Vector2::new(400.0, 38.0),
+                        cgmath::Vector4::new(1.0, 0.5, 1.0, 1.0))];
+                // font_instances.extend(world.debug_objects.iter().map(|i| {
+                //     (
+                //         format!("{}", i.overlaps),
+                //         render.camera.world_to_view_pos(cgmath::Point2::new(0.0, 0.0) + i.position),
+                //         cgmath::Vector4::new(i.color.x, i.color.y, i.color.z, 0.8)
+                //     )
+                // }));
+                self.font_renderer.render(font, render.queue, &mut render_pass, &ui_camera,
+                    &font_instances)?;
+            }
+
+            // render the overlay for the current game state over a dimmed world
+            if let Overlay::Text(title, subtitle) = &overlay {
+                let window_size = Vector2::new(render.camera.window_size.x as f32, render.camera.window_size.y as f32);
+                self.ui_texture_renderer.render(
+                    render.queue,
+                    &mut render_pass,
+                    &ui_camera,
+                    vec![
+                        (vec![Instance {
+                            position: window_size / 2.0,
+                            scale: window_size,
+                            color: Vector4::new(0.0, 0.0, 0.0, 0.5),
+                            ..Default::default()
+                        }], solid_texture)
+                    ]
+                )?;
+                let line_height = font.line_height();
+                let centered = |text: &str, y: f32| Vector2::new((window_size.x - font.text_width(text)) / 2.0, y);
+                let overlay_text = vec![
+                    (title.clone(), centered(&title, window_size.y / 2.0), Vector4::new(1.0, 0.9, 0.6, 1.0)),
+                    (subtitle.clone(), centered(&subtitle, window_size.y / 2.0 + line_height * 1.5), Vector4::new(1.0, 1.0, 1.0, 1.0)),
+                ];
+                self.font_renderer.render(font, render.queue, &mut render_pass, &ui_camera,
+                    &overlay_text)?;
+            }
 
             // render ui
+            self.render_ui_commands(render.queue, &mut render_pass, &ui_camera, assets, ui_commands)?;
+
             // render chatbox
-            let (background_instance, chatbox_text_instances) =
+            let (chatbox_instances, chatbox_text_instances) =
                 chatbox.render();
 
             self.ui_texture_renderer.render(
@@ -273,12 +359,12 @@ impl RenderEngine {
                 &mut render_pass,
                 &ui_camera,
                 vec![
-                    (vec![background_instance], &self.solid_texture)
+                    (chatbox_instances, solid_texture)
                 ]
             )?;
 
             self.font_renderer.render(
-                &self.font,
+                font,
                 render.queue,
                 &mut render_pass,
                 &ui_camera,
@@ -293,3 +379,62 @@ impl RenderEngine {
         Ok(())
     }
 }
+
+fn begin_render_pass<'a>(encoder: &'a mut wgpu::CommandEncoder, view: &'a wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: true,
+                },
+            })
+        ],
+        depth_stencil_attachment: None,
+    })
+}
+
+// where the current frame of an animator is in the atlas, and whether to flip it because the art faces the other way.
+// clips that aren't defined show all of fallback, which faces right like clips do by default
+fn animation_frame(animations: &Animations, atlas: &Atlas, animator: &Animator, facing_left: bool, fallback: &str) -> (UvRect, bool) {
+    let clip = animations.get(animator.clip()).filter(|clip| atlas.contains(&clip.sheet));
+    match clip.and_then(|clip| clip.frame_at(animator.time()).map(|frame| (clip, frame))) {
+        Some((clip, frame)) =>
+            (atlas.sub_region(&clip.sheet, frame.x, frame.y, frame.width, frame.height), clip.faces_left != facing_left),
+        None => (atlas.region(fallback), facing_left),
+    }
+}
+
+impl RenderEngine {
+    // draws consecutive rects and texts in batches, keeping the commands' back to front order
+    fn render_ui_commands<'a>(&'a self, queue: &mut wgpu::Queue, render_pass: &mut wgpu::RenderPass<'a>, camera: &UICamera, assets: &'a Assets, commands: &[DrawCommand]) -> Result<(), wgpu::SurfaceError> {
+        for batch in ui::batches(commands) {
+            if matches!(batch[0], DrawCommand::Rect { .. }) {
+                let instances = batch.iter().filter_map(|command| match command {
+                    DrawCommand::Rect { rect, color } => Some(Instance {
+                        position: rect.center(),
+                        scale: rect.size,
+                        color: *color,
+                        ..Default::default()
+                    }),
+                    _ => None,
+                }).collect();
+                self.ui_texture_renderer.render(queue, render_pass, camera, vec![(instances, assets.texture(assets.blank))])?;
+            } else {
+                let scale = match batch[0] {
+                    DrawCommand::Text { scale, .. } => scale,
+                    _ => 1.0,
+                };
+                let texts = batch.iter().filter_map(|command| match command {
+                    DrawCommand::Text { text, position, color, .. } => Some((text.clone(), *position, *color)),
+                    _ => None,
+                }).collect();
+                self.font_renderer.render_scaled(assets.font(self.font), queue, render_pass, camera, &texts, scale)?;
+            }
+        }
+        Ok(())
+    }
+}
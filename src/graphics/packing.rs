@@ -0,0 +1,249 @@
+// packs rectangles into the smallest power of two square that fits them, used for font glyphs and sprite atlases
+use std::{hash::Hash, collections::HashMap, cmp};
+use cgmath::Vector2;
+
+pub type Coord = u32;
+
+pub trait RectSize<ID> {
+    fn id(&self) -> ID;
+    fn width(&self) -> Coord;
+    fn height(&self) -> Coord;
+}
+
+pub struct RectInfo<ID: Eq + Hash> {
+    pub id: ID,
+    pub width: Coord,
+    pub height: Coord
+}
+
+impl<ID: Eq + Hash + Clone> RectInfo<ID> {
+    fn copy<T: RectSize<ID>>(rect: &T) -> RectInfo<ID> {
+        RectInfo {
+            id: rect.id(),
+            width: rect.width(),
+            height: rect.height()
+        }
+    }
+}
+
+impl<ID> RectSize<ID> for RectInfo<ID> where ID: Eq + Hash + Clone {
+    fn id(&self) -> ID {
+        self.id.clone()
+    }
+    fn width(&self) -> Coord {
+        self.width
+    }
+    fn height(&self) -> Coord {
+        self.height
+    }
+}
+
+pub struct Packing<ID> where ID: Eq + Hash + Clone {
+    width: Coord,
+    height: Coord,
+    pos_map: HashMap<ID, Vector2<Coord>>
+}
+
+impl<ID> Packing<ID> where ID: Eq + Hash + Clone {
+    pub fn width(&self) -> Coord {
+        self.width
+    }
+    pub fn height(&self) -> Coord {
+        self.height
+    }
+    pub fn get_pos(&self, id: ID) -> Option<Vector2<Coord>> {
+        self.pos_map.get(&id).copied()
+    }
+
+    // whether every rect was placed inside the bounds without overlapping another
+    pub fn is_valid<T: RectSize<ID>>(&self, rects: &[T]) -> bool {
+        let placed: Option<Vec<(Vector2<Coord>, &T)>> = rects.iter()
+            .map(|rect| self.get_pos(rect.id()).map(|pos| (pos, rect)))
+            .collect();
+        let placed = match placed {
+            Some(placed) => placed,
+            None => return false,
+        };
+        let in_bounds = placed.iter().all(|(pos, rect)|
+            pos.x + rect.width() <= self.width && pos.y + rect.height() <= self.height);
+        let overlapping = placed.iter().enumerate().any(|(i, (a, a_rect))|
+            placed[i + 1..].iter().any(|(b, b_rect)|
+                a.x < b.x + b_rect.width() && b.x < a.x + a_rect.width()
+                    && a.y < b.y + b_rect.height() && b.y < a.y + a_rect.height()));
+        in_bounds && !overlapping
+    }
+}
+
+// tries square sizes from 2^min_log to 2^max_log
+pub fn pack<ID, T>(rects: &[T], min_log: Coord, max_log: Coord) -> Option<Packing<ID>>
+        where ID: Eq + Hash + Clone, T: RectSize<ID> {
+    let mut rects_copy: Vec<RectInfo<ID>> = rects.iter().map(|rect: &T| RectInfo::copy(rect)).collect();
+    // sort descending height, then descending width
+    rects_copy.sort_by(|a, b| b.height().cmp(&a.height()).then(b.width().cmp(&a.width())));
+    // try to pack the rects
+    recursive(&rects_copy, min_log, max_log)
+}
+
+pub fn do_font_packing<ID, T>(glyphs: &[T]) -> Option<Packing<ID>>
+        where ID: Eq + Hash + Clone, T: RectSize<ID> {
+    pack(glyphs, 6, 14)
+}
+
+fn recursive<ID, T>(rects: &[T], min: Coord, max: Coord) -> Option<Packing<ID>>
+        where ID: Eq + Hash + Clone, T: RectSize<ID> {
+    assert!(min < 32 && max < 32);
+    if max == min {
+        // only one option
+        let size = (2 as Coord).pow(min);
+        get_packing(rects, size, size)
+    } else {
+        let log_size = (min + max + 1) / 2; // ceiling division
+        let size = (2 as Coord).pow(log_size);
+        match get_packing(rects, size, size) {
+            Some(packing) =>
+                // valid try smaller
+                match recursive(rects, min, cmp::max(log_size - 1, min)) {
+                    Some(smaller) => Some(smaller),
+                    None => Some(packing)
+                },
+            None =>
+                // invalid go bigger
+                if log_size == max {
+                    None // already biggest - this should affect the case
+                         // where the range is 2 sizes. without this,
+                         // if both values are fails, the bigger will calculate twice
+                } else {
+                    recursive(rects, cmp::min(log_size + 1, max), max)
+                }
+        }
+    }
+}
+
+// a horizontal run of the skyline: everything below y is taken from x to x + width
+#[derive(Clone, Copy)]
+struct Segment {
+    x: Coord,
+    y: Coord,
+    width: Coord,
+}
+
+// the lowest y a rect of this width can sit at with its left edge on segment i, if it fits horizontally
+fn skyline_fit(skyline: &[Segment], i: usize, width: Coord, bin_width: Coord) -> Option<Coord> {
+    let x = skyline[i].x;
+    if x + width > bin_width {
+        return None
+    }
+    let mut y = 0;
+    let mut covered = 0;
+    for segment in &skyline[i..] {
+        if covered >= width {
+            break;
+        }
+        y = cmp::max(y, segment.y);
+        covered = segment.x + segment.width - x;
+    }
+    Some(y)
+}
+
+// skyline bottom left packing: each rect goes where its top edge ends up lowest, leftmost on ties.
+// wastes much less space than filling rows when the heights vary
+fn get_packing<ID, T>(rects: &[T], width: Coord, height: Coord) -> Option<Packing<ID>>
+        where ID: Eq + Hash + Clone, T: RectSize<ID> {
+    let mut map: HashMap<ID, Vector2<Coord>> = HashMap::new();
+    let mut skyline = vec![Segment { x: 0, y: 0, width }];
+    for rect in rects {
+        let (w, h) = (rect.width(), rect.height());
+        // find the best segment to start on
+        let best = (0..skyline.len())
+            .filter_map(|i| skyline_fit(&skyline, i, w, width).map(|y| (i, y)))
+            .filter(|(_, y)| y + h <= height)
+            .min_by_key(|(i, y)| (y + h, skyline[*i].x))?;
+        let (i, y) = best;
+        let x = skyline[i].x;
+        map.insert(rect.id(), Vector2::new(x, y));
+        if w == 0 {
+            continue;
+        }
+
+        // raise the skyline under the rect, trimming the segments it covers
+        skyline.insert(i, Segment { x, y: y + h, width: w });
+        let right = x + w;
+        while i + 1 < skyline.len() && skyline[i + 1].x < right {
+            let next = &mut skyline[i + 1];
+            let next_right = next.x + next.width;
+            if next_right <= right {
+                skyline.remove(i + 1);
+            } else {
+                next.width = next_right - right;
+                next.x = right;
+            }
+        }
+        // merge neighbors at the same height
+        let mut j = 0;
+        while j + 1 < skyline.len() {
+            if skyline[j].y == skyline[j + 1].y {
+                skyline[j].width += skyline[j + 1].width;
+                skyline.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+    }
+    Some(Packing {
+        width,
+        height,
+        pos_map: map
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(id: usize, width: Coord, height: Coord) -> RectInfo<usize> {
+        RectInfo { id, width, height }
+    }
+
+    #[test]
+    fn packs_mixed_sizes_without_overlap() {
+        // a spread of sizes from a simple lcg so the test doesn't need rand
+        let mut seed: u32 = 12345;
+        let mut next = |max: Coord| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            1 + (seed >> 16) % max
+        };
+        let rects: Vec<_> = (0..200).map(|i| rect(i, next(40), next(40))).collect();
+        let packing = pack(&rects, 6, 12).expect("200 rects of up to 40x40 fit in 4096x4096");
+        assert!(packing.is_valid(&rects));
+    }
+
+    #[test]
+    fn zero_width_rect_is_placed() {
+        let rects = vec![rect(0, 0, 10), rect(1, 30, 30), rect(2, 5, 0)];
+        let packing = pack(&rects, 6, 8).unwrap();
+        assert!(packing.is_valid(&rects));
+        assert!(packing.get_pos(0).is_some());
+        assert!(packing.get_pos(2).is_some());
+    }
+
+    #[test]
+    fn rect_wider_than_the_largest_bin_does_not_fit() {
+        let rects = vec![rect(0, 300, 10)];
+        assert!(pack(&rects, 6, 8).is_none());
+    }
+
+    #[test]
+    fn picks_the_smallest_power_of_two() {
+        // four 32x32 rects exactly fill 64x64
+        let rects: Vec<_> = (0..4).map(|i| rect(i, 32, 32)).collect();
+        let packing = pack(&rects, 4, 10).unwrap();
+        assert_eq!((packing.width(), packing.height()), (64, 64));
+        assert!(packing.is_valid(&rects));
+
+        // one more doesn't fit in 64x64, so it takes 128x128
+        let rects: Vec<_> = (0..5).map(|i| rect(i, 32, 32)).collect();
+        let packing = pack(&rects, 4, 10).unwrap();
+        assert_eq!((packing.width(), packing.height()), (128, 128));
+        assert!(packing.is_valid(&rects));
+    }
+}
@@ -0,0 +1,251 @@
+// background layers drawn behind the level, each moving some fraction of the way with the camera so
+// far away layers seem to move slower. defined per level in assets/backgrounds.cfg
+use std::collections::{BTreeMap, HashMap};
+
+use cgmath::{Vector2, ElementWise};
+
+use crate::assets::{Assets, Handle};
+use super::{texture::Texture, textured::Instance};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackgroundLayer {
+    pub texture: String,
+    // world units tall one copy of the texture is drawn, its width keeps the texture's aspect ratio
+    pub height: f32,
+    // how much the layer moves with the camera, 0 stays put on the screen and 1 moves with the world
+    pub parallax: Vector2<f32>,
+    // where the first copy is centered when the camera is at the origin
+    pub offset: Vector2<f32>,
+    pub repeat_x: bool,
+    pub repeat_y: bool,
+    // world units per second the layer drifts by on its own
+    pub scroll: Vector2<f32>,
+}
+
+impl BackgroundLayer {
+    // instances covering a view of half_extent around center, time seconds into the level.
+    // size is the world size of one copy of the texture
+    pub fn instances(&self, size: Vector2<f32>, center: Vector2<f32>, half_extent: Vector2<f32>, time: f32) -> Vec<Instance> {
+        // the camera moving by d moves the layer by d * (1 - parallax) in the world, so it moves d * parallax on the screen
+        let origin = center.mul_element_wise(Vector2::new(1.0, 1.0) - self.parallax) + self.offset + self.scroll * time;
+        // the copies that overlap the view on an axis, copy i is centered at origin + i * size
+        let copies = |repeat: bool, origin: f32, size: f32, center: f32, half: f32| if repeat && size > 0.0 {
+            ((center - half - origin) / size + 0.5).floor() as i32..=((center + half - origin) / size + 0.5).floor() as i32
+        } else {
+            0..=0
+        };
+        let mut instances = vec![];
+        for y in copies(self.repeat_y, origin.y, size.y, center.y, half_extent.y) {
+            for x in copies(self.repeat_x, origin.x, size.x, center.x, half_extent.x) {
+                instances.push(Instance {
+                    position: origin + Vector2::new(x as f32, y as f32).mul_element_wise(size),
+                    scale: size,
+                    ..Default::default()
+                });
+            }
+        }
+        instances
+    }
+}
+
+fn last_layer(levels: &mut BTreeMap<u32, Vec<BackgroundLayer>>, level: Option<u32>) -> Option<&mut BackgroundLayer> {
+    levels.get_mut(&level?)?.last_mut()
+}
+
+#[derive(Default)]
+pub struct Backgrounds {
+    // the layers of each level that defines them, back to front
+    levels: BTreeMap<u32, Vec<BackgroundLayer>>,
+    textures: HashMap<String, Handle<Texture>>,
+}
+
+impl Backgrounds {
+    pub const PATH: &'static str = "backgrounds.cfg";
+
+    // levels without their own layers use the ones of the closest level before them
+    pub fn layers(&self, level: u32) -> &[BackgroundLayer] {
+        self.levels.range(..=level).next_back().map_or(&[], |(_, layers)| layers)
+    }
+
+    // the texture a layer draws, none if it couldn't be loaded
+    pub fn texture(&self, layer: &BackgroundLayer) -> Option<Handle<Texture>> {
+        self.textures.get(&layer.texture).copied()
+    }
+
+    // "level <number>" starts the layers of a level and each "layer <texture> <height> <parallax x> <parallax y>"
+    // after it adds one in front of the last. "offset <x> <y>", "repeat <x|y|xy>" and "scroll <x> <y>" change the layer above them.
+    // blank lines and lines starting with # are skipped
+    pub fn from_config_str(config: &str) -> Result<Backgrounds, String> {
+        let mut levels: BTreeMap<u32, Vec<BackgroundLayer>> = BTreeMap::new();
+        let mut current = None;
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let float = |value: &str| value.parse::<f32>().map_err(|_| error(&format!("{} is not a number", value)));
+            let no_layer = || error("expected a layer before this line");
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["level", level] => {
+                    let level = level.parse::<u32>().map_err(|_| error(&format!("{} is not a level", level)))?;
+                    levels.entry(level).or_default();
+                    current = Some(level);
+                },
+                ["layer", texture, height, parallax_x, parallax_y] => {
+                    let height = float(height)?;
+                    if height <= 0.0 {
+                        return Err(error("height must be positive"))
+                    }
+                    let layer = BackgroundLayer {
+                        texture: texture.to_string(),
+                        height,
+                        parallax: Vector2::new(float(parallax_x)?, float(parallax_y)?),
+                        offset: Vector2::new(0.0, 0.0),
+                        repeat_x: false,
+                        repeat_y: false,
+                        scroll: Vector2::new(0.0, 0.0),
+                    };
+                    let level = current.ok_or_else(|| error("layer before any level"))?;
+                    levels.entry(level).or_default().push(layer);
+                },
+                ["offset", x, y] => {
+                    let offset = Vector2::new(float(x)?, float(y)?);
+                    last_layer(&mut levels, current).ok_or_else(no_layer)?.offset = offset;
+                },
+                ["repeat", axes] => {
+                    let (x, y) = match axes {
+                        "x" => (true, false),
+                        "y" => (false, true),
+                        "xy" => (true, true),
+                        _ => return Err(error("expected x, y or xy")),
+                    };
+                    let layer = last_layer(&mut levels, current).ok_or_else(no_layer)?;
+                    layer.repeat_x = x;
+                    layer.repeat_y = y;
+                },
+                ["scroll", x, y] => {
+                    let scroll = Vector2::new(float(x)?, float(y)?);
+                    last_layer(&mut levels, current).ok_or_else(no_layer)?.scroll = scroll;
+                },
+                _ => return Err(error("expected level, layer, offset, repeat or scroll")),
+            }
+        }
+        Ok(Backgrounds { levels, textures: HashMap::new() })
+    }
+
+    // loads the texture of every layer too. falls back to no layers, and layers whose texture is missing are skipped
+    pub fn load(device: &wgpu::Device, queue: &wgpu::Queue, assets: &mut Assets) -> Backgrounds {
+        let parsed = assets.load_data(Self::PATH)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|bytes| Backgrounds::from_config_str(&String::from_utf8_lossy(&bytes)));
+        let mut backgrounds = parsed.unwrap_or_else(|err| {
+            log::warn!("Couldn't load {}: {}", Self::PATH, err);
+            Backgrounds::default()
+        });
+        let paths: Vec<String> = backgrounds.levels.values().flatten().map(|layer| layer.texture.clone()).collect();
+        for path in paths {
+            if backgrounds.textures.contains_key(&path) {
+                continue
+            }
+            match assets.load_texture(device, queue, &path, wgpu::FilterMode::Nearest) {
+                Ok(handle) => {
+                    backgrounds.textures.insert(path, handle);
+                },
+                Err(err) => log::warn!("Couldn't load background {}: {:#}", path, err),
+            }
+        }
+        backgrounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(repeat_x: bool, repeat_y: bool) -> BackgroundLayer {
+        BackgroundLayer {
+            texture: "hills.png".to_string(),
+            height: 4.0,
+            parallax: Vector2::new(0.5, 0.25),
+            offset: Vector2::new(0.3, -0.7),
+            repeat_x,
+            repeat_y,
+            scroll: Vector2::new(1.5, 0.0),
+        }
+    }
+
+    #[test]
+    fn parses_levels_and_layers() {
+        let config = "# comment\n\nlevel 1\nlayer sky.png 10 0 0\nlayer hills.png 4 0.5 0.25\noffset 0.3 -0.7\nrepeat x\nscroll 1.5 0\nlevel 3\nlayer cave.png 8 1 1\nrepeat xy\n";
+        let backgrounds = Backgrounds::from_config_str(config).unwrap();
+        let layers = backgrounds.layers(1);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].texture, "sky.png");
+        assert_eq!(layers[1], layer(true, false));
+        assert!(backgrounds.layers(3)[0].repeat_x && backgrounds.layers(3)[0].repeat_y);
+    }
+
+    #[test]
+    fn levels_fall_back_to_the_closest_one_before_them() {
+        let backgrounds = Backgrounds::from_config_str("level 2\nlayer a.png 1 0 0\nlevel 5\nlayer b.png 1 0 0").unwrap();
+        assert!(backgrounds.layers(1).is_empty());
+        assert_eq!(backgrounds.layers(2)[0].texture, "a.png");
+        assert_eq!(backgrounds.layers(4)[0].texture, "a.png");
+        assert_eq!(backgrounds.layers(9)[0].texture, "b.png");
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let error = |config: &str| Backgrounds::from_config_str(config).err().unwrap();
+        assert_eq!(error("layer a.png 1 0 0"), "line 1: layer before any level");
+        assert_eq!(error("level 1\noffset 1 2"), "line 2: expected a layer before this line");
+        assert_eq!(error("level 1\nscroll 1 2"), "line 2: expected a layer before this line");
+        assert_eq!(error("level 1\n\nrepeat x"), "line 3: expected a layer before this line");
+        // a new level has no layer yet for the directives to change
+        assert_eq!(error("level 1\nlayer a.png 1 0 0\nlevel 2\nrepeat x"), "line 4: expected a layer before this line");
+        assert_eq!(error("level 1\nlayer a.png 1 0 0\nrepeat z"), "line 3: expected x, y or xy");
+        assert_eq!(error("level 1\nlayer a.png 0 0 0"), "line 2: height must be positive");
+        assert_eq!(error("level 1\nlayer a.png -2 0 0"), "line 2: height must be positive");
+        assert_eq!(error("level 1\nlayer a.png tall 0 0"), "line 2: tall is not a number");
+        assert_eq!(error("level one"), "line 1: one is not a level");
+        assert_eq!(error("level 1\nfog"), "line 2: expected level, layer, offset, repeat or scroll");
+    }
+
+    #[test]
+    fn single_layers_draw_one_copy() {
+        let instances = layer(false, false).instances(Vector2::new(6.0, 4.0), Vector2::new(40.0, -3.0), Vector2::new(10.0, 8.0), 2.0);
+        assert_eq!(instances.len(), 1);
+        // half the camera movement plus the offset and the scroll
+        assert_eq!(instances[0].position, Vector2::new(20.0 + 0.3 + 3.0, -2.25 - 0.7));
+    }
+
+    #[test]
+    fn tiled_layers_cover_the_view_without_gaps() {
+        let size = Vector2::new(6.0, 4.0);
+        let half_extent = Vector2::new(10.0, 8.0);
+        let layer = layer(true, true);
+        for step in 0..200 {
+            // includes views whose edges land exactly on copy edges
+            let center = Vector2::new(step as f32 * 0.37 - 30.0, step as f32 * -0.5 + 20.0);
+            let time = step as f32 * 0.1;
+            let instances = layer.instances(size, center, half_extent, time);
+            let mut xs: Vec<f32> = instances.iter().map(|instance| instance.position.x).collect();
+            let mut ys: Vec<f32> = instances.iter().map(|instance| instance.position.y).collect();
+            for (positions, size, center, half) in [(&mut xs, size.x, center.x, half_extent.x), (&mut ys, size.y, center.y, half_extent.y)] {
+                positions.sort_by(f32::total_cmp);
+                positions.dedup();
+                for pair in positions.windows(2) {
+                    assert!((pair[1] - pair[0] - size).abs() < 1e-3, "gap between copies at {:?}", center);
+                }
+                let (first, last) = (positions[0], positions[positions.len() - 1]);
+                assert!(first - size / 2.0 <= center - half + 1e-3, "uncovered start at {:?}", center);
+                assert!(last + size / 2.0 >= center + half - 1e-3, "uncovered end at {:?}", center);
+                // no copy is entirely outside the view
+                assert!(first + size / 2.0 >= center - half - 1e-3);
+                assert!(last - size / 2.0 <= center + half + 1e-3);
+            }
+            assert_eq!(instances.len(), xs.len() * ys.len());
+        }
+    }
+}
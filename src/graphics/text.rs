@@ -3,12 +3,10 @@ use crate::camera::CameraObj;
 use crate::{util::PartialOrdMinMax, camera::Camera};
 use crate::graphics::texture::Texture;
 
-use self::packing::{GlyphPacking, GlyphInfo};
+use super::packing::{self, Packing, RectInfo};
 use cgmath::{Matrix4, Vector2, SquareMatrix};
 use wgpu::util::DeviceExt;
 
-mod packing;
-
 pub const MAX_GLYPHS_PER_FRAME: u32 = 8192;
 
 pub fn default_characters() -> Vec<char> {
@@ -79,8 +77,8 @@ pub fn make_font_infos<'a, T>(bytes: &[u8], font_sizes: &[f32], char_codes: T, n
         // make this padded thing pad each glyph by 1 px, and then send it to do_font_packing
         //     note that if we pad on the bottom and right edges for every box, then every box
         //     ends up with an exactly 1px boundary
-        let padded: Vec<GlyphInfo<char>> = glyphs.iter().map(|glyph| {
-            GlyphInfo {
+        let padded: Vec<RectInfo<char>> = glyphs.iter().map(|glyph| {
+            RectInfo {
                 id: glyph.char_code,
                 width: glyph.width as u32 + 1,
                 height: glyph.height as u32 + 1
@@ -105,7 +103,7 @@ pub fn make_font_infos<'a, T>(bytes: &[u8], font_sizes: &[f32], char_codes: T, n
                 glyph.char_code,
                 GlyphMetrics {
                     glyph_pos: {
-                        let v = packing.get_glyph_pos(glyph.char_code).unwrap();
+                        let v = packing.get_pos(glyph.char_code).unwrap();
                         Vector2::new(v.x as f32, v.y as f32)
                     },
                     glyph_size: Vector2::new(glyph.width as f32,
@@ -127,13 +125,13 @@ pub fn make_font_infos<'a, T>(bytes: &[u8], font_sizes: &[f32], char_codes: T, n
 }
 
 // applies packing by copying glyphs to positions specified by the packing into a new vector
-fn apply_packing(glyphs: &Vec<GlyphBitmap>, packing: &GlyphPacking<char>) -> Vec<u8> {
+fn apply_packing(glyphs: &Vec<GlyphBitmap>, packing: &Packing<char>) -> Vec<u8> {
     let mut image: Vec<u8> = Vec::new();
     let width: usize = packing.width().try_into().unwrap();
     let height: usize = packing.height().try_into().unwrap();
     image.resize(width * height, 0);
     for glyph in glyphs {
-        let uncv_l = packing.get_glyph_pos(glyph.char_code).unwrap();
+        let uncv_l = packing.get_pos(glyph.char_code).unwrap();
         let location: Vector2<usize> = Vector2::new(
             uncv_l.x.try_into().unwrap(),
             uncv_l.y.try_into().unwrap());
@@ -573,6 +571,11 @@ impl TextureBindGroups {
     pub fn get_texture_bind_group<'a>(&'a self, texture: &Texture) -> Option<&'a wgpu::BindGroup> {
         self.texture_bind_groups.get(&texture.id)
     }
+
+    // drops the bind group of a texture that was replaced, the next make_texture_bind_group remakes it
+    pub fn remove_texture_bind_group(&mut self, id: &str) {
+        self.texture_bind_groups.remove(id);
+    }
 }
 
 pub struct FontRenderer {
@@ -706,11 +709,20 @@ impl FontRenderer {
         self.texture_bind_groups.make_texture_bind_group(device, &font.sprite_texture);
     }
 
+    pub fn unregister_font(&mut self, id: &str) {
+        self.texture_bind_groups.remove_texture_bind_group(id);
+    }
+
     pub fn reset(&self) {
         self.current_buffer_pos.set(0);
     }
 
     pub fn render<'a, C: CameraObj>(&'a self, font: &Font, queue: &wgpu::Queue, render_pass: &mut wgpu::RenderPass<'a>, camera: &C, instances: &Vec<(String, cgmath::Vector2<f32>, cgmath::Vector4<f32>)>) -> Result<(), wgpu::SurfaceError> {
+        self.render_scaled(font, queue, render_pass, camera, instances, 1.0)
+    }
+
+    // same as render, with text scaled up by scale around each text's position
+    pub fn render_scaled<'a, C: CameraObj>(&'a self, font: &Font, queue: &wgpu::Queue, render_pass: &mut wgpu::RenderPass<'a>, camera: &C, instances: &Vec<(String, cgmath::Vector2<f32>, cgmath::Vector4<f32>)>, scale: f32) -> Result<(), wgpu::SurfaceError> {
             
         // retrieve bind group for the given texture
         let diffuse_bind_group = self.texture_bind_groups.get_texture_bind_group(&font.sprite_texture)
@@ -719,7 +731,7 @@ impl FontRenderer {
 
         // split instances apart and reformat them
         let instances_calc = instances.iter().flat_map(|(text, pos, color)| {
-            let base = proj * Matrix4::from_translation(cgmath::Vector3::new(pos.x, pos.y, 0.0));
+            let base = proj * Matrix4::from_translation(cgmath::Vector3::new(pos.x, pos.y, 0.0)) * Matrix4::from_scale(scale);
             let mut line_width = 0.0;
             let line_height = font.line_height();
             let mut trans: Matrix4<f32> = Matrix4::identity();
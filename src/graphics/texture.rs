@@ -3,6 +3,7 @@ use anyhow::*;
 
 pub struct Texture {
     pub id: String,
+    pub dimensions: (u32, u32),
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
@@ -100,9 +101,73 @@ impl Texture {
             sampler,
             view,
             id: String::from(id),
+            dimensions,
         })
     }
 
+    // a texture that can be drawn into and then drawn like any other, in the format of the surface so the same pipelines work
+    pub fn render_target(device: &wgpu::Device, dimensions: (u32, u32), format: wgpu::TextureFormat, id: &str) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some((String::from("render_target_") + id).as_str()),
+            size: wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            sampler,
+            view,
+            id: String::from(id),
+            dimensions,
+        }
+    }
+
+    // replaces the pixels of an rgba texture in place, so bind groups made for it stay valid
+    pub fn write_image(&self, queue: &wgpu::Queue, img: &image::DynamicImage) -> Result<()> {
+        let dimensions = img.dimensions();
+        if dimensions != self.dimensions {
+            bail!("size changed from {:?} to {:?}", self.dimensions, dimensions);
+        }
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &img.to_rgba8(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
+                rows_per_image: std::num::NonZeroU32::new(dimensions.1),
+            },
+            wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -9,6 +9,8 @@ struct InstanceInput {
     @location(7) model_matrix_2: vec4<f32>,
     @location(8) model_matrix_3: vec4<f32>, 
     @location(9) color: vec4<f32>,
+    @location(10) uv: vec4<f32>,
+    @location(11) flip_pivot: vec4<f32>,
 };
 
 @group(1) @binding(0)
@@ -37,8 +39,10 @@ fn vs_main(
         instance.model_matrix_3,
     );
     var out: VertexOutput;
-    out.tex_coords = model.tex_coords;
-    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+    let tex_coords = vec2<f32>(0.5, 0.5) + (model.tex_coords - vec2<f32>(0.5, 0.5)) * instance.flip_pivot.xy;
+    out.tex_coords = instance.uv.xy + tex_coords * instance.uv.zw;
+    let position = vec3<f32>(model.position.xy - instance.flip_pivot.zw, model.position.z);
+    out.clip_position = camera.view_proj * model_matrix * vec4<f32>(position, 1.0);
     out.color = instance.color;
     return out;
 }
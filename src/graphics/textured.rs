@@ -6,7 +6,8 @@ use wgpu::util::DeviceExt;
 use crate::{camera::CameraObj, graphics::texture::Texture};
 
 const INSTANCE_BUFFERS: u32 = 64;
-const INSTANCE_BUFFER_DEFAULT: [InstanceRaw; 16] = [InstanceRaw { model: [[0.0; 4]; 4], color: [0.0; 4] }; 16];
+// instances drawn per draw call, more than this with one texture are split over several
+const INSTANCE_BUFFER_SIZE: usize = 1024;
 
 use std::cell::Cell;
 
@@ -66,12 +67,57 @@ impl Vertex {
     }
 }
 
+// part of a texture in texture coordinates, (0, 0) is the top left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub offset: cgmath::Vector2<f32>,
+    pub size: cgmath::Vector2<f32>,
+}
+
+impl Default for UvRect {
+    // the whole texture
+    fn default() -> Self {
+        Self {
+            offset: cgmath::Vector2::new(0.0, 0.0),
+            size: cgmath::Vector2::new(1.0, 1.0),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Instance {
     pub position: cgmath::Vector2<f32>,
     pub scale: cgmath::Vector2<f32>,
-    // pub rotation: f32,
+    // radians clockwise around the pivot, since y points down
+    pub rotation: f32,
     pub color: cgmath::Vector4<f32>,
+    pub uv: UvRect,
+    // mirror the texture, the quad stays where it is
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // the point of the quad placed at position, from (-0.5, -0.5) at the top left to (0.5, 0.5) at the bottom right.
+    // it is also what the quad rotates around
+    pub pivot: cgmath::Vector2<f32>,
+}
+
+impl Instance {
+    pub const PIVOT_CENTER: cgmath::Vector2<f32> = cgmath::Vector2 { x: 0.0, y: 0.0 };
+    pub const PIVOT_BOTTOM: cgmath::Vector2<f32> = cgmath::Vector2 { x: 0.0, y: 0.5 };
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            position: cgmath::Vector2::new(0.0, 0.0),
+            scale: cgmath::Vector2::new(1.0, 1.0),
+            rotation: 0.0,
+            color: cgmath::Vector4::new(1.0, 1.0, 1.0, 1.0),
+            uv: UvRect::default(),
+            flip_x: false,
+            flip_y: false,
+            pivot: Instance::PIVOT_CENTER,
+        }
+    }
 }
 
 #[repr(C)]
@@ -79,6 +125,10 @@ pub struct Instance {
 struct InstanceRaw {
     model: [[f32; 4]; 4],
     color: [f32; 4],
+    // offset in xy, size in zw
+    uv: [f32; 4],
+    // -1 to flip in xy, pivot in zw
+    flip_pivot: [f32; 4],
 }
 
 impl Instance {
@@ -86,14 +136,17 @@ impl Instance {
         let translation = cgmath::Matrix4::from_translation(
             cgmath::Vector3::new(self.position.x, self.position.y, 0.0)
         );
-        // let rotation = //cgmath::Matrix4::from_angle_z(cgmath::Rad(self.rotation));
-        //             cgmath::Matrix4::identity();
+        let rotation = cgmath::Matrix4::from_angle_z(cgmath::Rad(self.rotation));
         let scaling = cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, 1.0);
-        let transf = translation * scaling;
+        // the shader moves the quad so the pivot is at the origin first
+        let transf = translation * rotation * scaling;
+        let flip = |flipped: bool| if flipped { -1.0 } else { 1.0 };
         InstanceRaw {
             model: transf.into(),
         //    model: cgmath::Matrix4::identity().into(),
             color: self.color.into(),
+            uv: [self.uv.offset.x, self.uv.offset.y, self.uv.size.x, self.uv.size.y],
+            flip_pivot: [flip(self.flip_x), flip(self.flip_y), self.pivot.x, self.pivot.y],
         }
     }
 }
@@ -139,6 +192,18 @@ impl InstanceRaw {
                     shader_location: 9,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // for the uv rect
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // for flipping and the pivot
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 24]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -183,6 +248,17 @@ impl TextureBindGroups {
     pub fn get_texture_bind_group<'a>(&'a self, texture: &Texture) -> Option<&'a wgpu::BindGroup> {
         self.texture_bind_groups.get(&texture.id)
     }
+
+    // drops the bind group of a texture that was replaced, the next make_texture_bind_group remakes it
+    pub fn remove_texture_bind_group(&mut self, id: &str) {
+        self.texture_bind_groups.remove(id);
+    }
+}
+
+// instances kept on the gpu between frames
+pub struct StaticInstances {
+    buffer: wgpu::Buffer,
+    count: u32,
 }
 
 pub struct TextureRenderer {
@@ -208,6 +284,10 @@ impl TextureRenderer {
         textures.for_each(|texture| self.texture_bind_groups.make_texture_bind_group(device, texture.into()));
     }
 
+    pub fn remove_texture(&mut self, id: &str) {
+        self.texture_bind_groups.remove_texture_bind_group(id);
+    }
+
     pub fn init(
         device: &wgpu::Device,
         _queue: &wgpu::Queue,
@@ -289,11 +369,12 @@ impl TextureRenderer {
 
         // create an instance buffer list
         let instance_buffer_list = (0..INSTANCE_BUFFERS).into_iter().map(|_| {
-            device.create_buffer_init(
-                &wgpu::util::BufferInitDescriptor {
+            device.create_buffer(
+                &wgpu::BufferDescriptor {
                     label: Some("instance_buffer"),
-                    contents: bytemuck::cast_slice(&INSTANCE_BUFFER_DEFAULT),
+                    size: (INSTANCE_BUFFER_SIZE * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
                     usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
                 }
             )
         }).collect::<Vec<_>>();
@@ -418,11 +499,11 @@ impl TextureRenderer {
 
         // split instances apart
         let mut instance_pairs = vec![];
-        for (mut instances, texture) in instance_pairs_input {
+        for (mut instances, texture) in batch_by_texture(instance_pairs_input) {
             while !instances.is_empty() {
                 instance_pairs.push(
                     (
-                        instances.drain(0..std::cmp::min(instances.len(), INSTANCE_BUFFER_DEFAULT.len()))
+                        instances.drain(0..std::cmp::min(instances.len(), INSTANCE_BUFFER_SIZE))
                             .collect::<Vec<_>>(),
                         texture
                     )
@@ -432,7 +513,7 @@ impl TextureRenderer {
         let mut current_buffer = self.current_buffer.get();
         if self.instance_buffer_list.len() < instance_pairs.len() + current_buffer {
             panic!("Gave too many instances even after grouping! {} groups. Can only support {} number of groups of {} size that share the same texture",
-                   instance_pairs.len(), INSTANCE_BUFFERS, INSTANCE_BUFFER_DEFAULT.len());
+                   instance_pairs.len(), INSTANCE_BUFFERS, INSTANCE_BUFFER_SIZE);
         }
         self.current_buffer.set(current_buffer + instance_pairs.len());
         for (instances, texture) in instance_pairs {
@@ -458,4 +539,58 @@ impl TextureRenderer {
         }
         Ok(())
     }
+
+    // uploads instances that don't change from frame to frame once, to be drawn with render_static
+    pub fn make_static_instances(&self, device: &wgpu::Device, instances: &[Instance]) -> StaticInstances {
+        let raw = instances.iter().map(|instance| instance.to_raw()).collect::<Vec<_>>();
+        StaticInstances {
+            buffer: device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("static_instance_buffer"),
+                    contents: bytemuck::cast_slice(&raw),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }
+            ),
+            count: instances.len() as u32,
+        }
+    }
+
+    pub fn render_static<'a, C: CameraObj>(&'a self, queue: &mut wgpu::Queue, render_pass: &mut wgpu::RenderPass<'a>, camera: &C, batches: &[&'a StaticInstances], texture: &Texture) -> Result<(), wgpu::SurfaceError> {
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(camera);
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[camera_uniform]));
+
+        let diffuse_bind_group = self.texture_bind_groups.get_texture_bind_group(texture)
+            .expect("Could not find texture bind group, did you forget to register your texture?");
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, diffuse_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.square_vertex_buffer.slice(..));
+        for batch in batches.iter().filter(|batch| batch.count > 0) {
+            render_pass.set_vertex_buffer(1, batch.buffer.slice(..));
+            render_pass.draw(0..self.square_num_vertices, 0..batch.count);
+        }
+        Ok(())
+    }
+}
+
+// joins neighboring pairs that use the same texture into one, so they share a draw call.
+// only neighbors are joined so instances still draw in the order given
+fn batch_by_texture(pairs: Vec<(Vec<Instance>, &Texture)>) -> Vec<(Vec<Instance>, &Texture)> {
+    merge_neighbors(pairs, |a, b| a.id == b.id)
+}
+
+// joins each pair into the one before it when same says their textures match, dropping empty pairs
+pub fn merge_neighbors<T>(pairs: impl IntoIterator<Item = (Vec<Instance>, T)>, same: impl Fn(&T, &T) -> bool) -> Vec<(Vec<Instance>, T)> {
+    let mut batches: Vec<(Vec<Instance>, T)> = vec![];
+    for (instances, texture) in pairs {
+        if instances.is_empty() {
+            continue;
+        }
+        match batches.last_mut() {
+            Some((batch, batch_texture)) if same(batch_texture, &texture) => batch.extend(instances),
+            _ => batches.push((instances, texture)),
+        }
+    }
+    batches
 }
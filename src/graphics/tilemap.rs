@@ -0,0 +1,80 @@
+// draws stage tiles from instance buffers cached per chunk, rebuilt only when a tile in or next to the chunk changes
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use cgmath::Vector2;
+use image::RgbaImage;
+use uuid::Uuid;
+
+use crate::world::{IDObject, stage::Stage};
+use super::{atlas, autotile, textured::{Instance, StaticInstances, TextureRenderer, UvRect}};
+
+struct Chunk {
+    revision: u32,
+    instances: StaticInstances,
+}
+
+pub struct Tilemap {
+    // where each autotile variant is in the tileset, in variant order
+    variants: Vec<UvRect>,
+    chunks: HashMap<(Uuid, Vector2<i32>), Chunk>,
+}
+
+impl Tilemap {
+    pub fn new(variants: Vec<UvRect>) -> Self {
+        Self {
+            variants,
+            chunks: HashMap::new(),
+        }
+    }
+
+    // draws every autotile variant of base into one image, returning it and where each variant is
+    pub fn make_tileset(base: &RgbaImage) -> Result<(RgbaImage, Vec<UvRect>)> {
+        let images: Vec<(String, RgbaImage)> = autotile::BLOB_MASKS.iter()
+            .map(|mask| (mask.to_string(), autotile::make_variant(base, *mask)))
+            .collect();
+        let (image, regions) = atlas::pack_images(&images)?;
+        let variants = images.iter().map(|(name, _)| regions[name]).collect();
+        Ok((image, variants))
+    }
+
+    // rebuilds chunks that changed since they were built and drops the ones of stages that are gone
+    pub fn update<'a>(&mut self, device: &wgpu::Device, renderer: &TextureRenderer, stages: impl Iterator<Item = &'a Stage>) {
+        let mut live = HashSet::new();
+        for stage in stages {
+            let id = stage.get_uuid();
+            for (chunk, revision) in stage.chunks() {
+                live.insert((id, chunk));
+                if self.chunks.get(&(id, chunk)).map_or(true, |built| built.revision != revision) {
+                    let instances = renderer.make_static_instances(device, &self.chunk_instances(stage, chunk));
+                    self.chunks.insert((id, chunk), Chunk { revision, instances });
+                }
+            }
+        }
+        self.chunks.retain(|key, _| live.contains(key));
+    }
+
+    pub fn chunks(&self) -> Vec<&StaticInstances> {
+        self.chunks.values().map(|chunk| &chunk.instances).collect()
+    }
+
+    fn chunk_instances(&self, stage: &Stage, chunk: Vector2<i32>) -> Vec<Instance> {
+        let origin = chunk * Stage::CHUNK_SIZE;
+        let mut instances = vec![];
+        for y in 0..Stage::CHUNK_SIZE {
+            for x in 0..Stage::CHUNK_SIZE {
+                let tile = origin + Vector2::new(x, y);
+                if stage.get_tile(&tile).is_none() {
+                    continue;
+                }
+                let mask = autotile::neighbor_mask(|dx, dy| stage.get_tile(&(tile + Vector2::new(dx, dy))).is_some());
+                instances.push(Instance {
+                    position: Vector2::new(tile.x as f32 + 0.5, tile.y as f32 + 0.5),
+                    uv: self.variants[autotile::variant(mask)],
+                    ..Default::default()
+                });
+            }
+        }
+        instances
+    }
+}
@@ -0,0 +1,113 @@
+// local high score table, kept in a file on native and in local storage on the web
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HighScoreEntry {
+    pub score: u32,
+    pub level: u32,
+    pub best_combo: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HighScores {
+    // highest first
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub const MAX_ENTRIES: usize = 10;
+    const FILE_PATH: &'static str = "highscores.txt";
+    #[cfg(target_arch = "wasm32")]
+    const STORAGE_KEY: &'static str = "wgputest_highscores";
+
+    // adds entry if it makes the table, returning its rank starting from 0
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        if entry.score == 0 {
+            return None
+        }
+        let rank = self.entries.iter().position(|e| entry.score > e.score).unwrap_or(self.entries.len());
+        if rank >= Self::MAX_ENTRIES {
+            return None
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(Self::MAX_ENTRIES);
+        Some(rank)
+    }
+
+    // records a run again as it goes on, dropping its old entry at previous so the run is only listed once
+    pub fn replace(&mut self, previous: Option<usize>, entry: HighScoreEntry) -> Option<usize> {
+        if let Some(previous) = previous.filter(|previous| *previous < self.entries.len()) {
+            self.entries.remove(previous);
+        }
+        self.insert(entry)
+    }
+
+    // one "score level best_combo" line per entry
+    pub fn to_save_string(&self) -> String {
+        self.entries.iter()
+            .map(|e| format!("{} {} {}\n", e.score, e.level, e.best_combo))
+            .collect()
+    }
+
+    // lines that don't parse are skipped rather than losing the whole table
+    pub fn from_save_str(save: &str) -> HighScores {
+        let mut entries: Vec<HighScoreEntry> = save.lines().filter_map(|line| {
+            match line.split_whitespace().map(|n| n.parse::<u32>()).collect::<Vec<_>>()[..] {
+                [Ok(score), Ok(level), Ok(best_combo)] => Some(HighScoreEntry { score, level, best_combo }),
+                _ => None,
+            }
+        }).collect();
+        entries.sort_by(|a, b| b.score.cmp(&a.score));
+        entries.truncate(Self::MAX_ENTRIES);
+        HighScores { entries }
+    }
+
+    pub fn load() -> HighScores {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                web_sys::window()
+                    .and_then(|window| window.local_storage().ok().flatten())
+                    .and_then(|storage| storage.get_item(Self::STORAGE_KEY).ok().flatten())
+                    .map(|save| HighScores::from_save_str(&save))
+                    .unwrap_or_default()
+            } else {
+                std::fs::read_to_string(Self::FILE_PATH)
+                    .map(|save| HighScores::from_save_str(&save))
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                let storage = web_sys::window()
+                    .and_then(|window| window.local_storage().ok().flatten())
+                    .ok_or_else(|| "local storage unavailable".to_string())?;
+                storage.set_item(Self::STORAGE_KEY, &self.to_save_string())
+                    .map_err(|err| format!("{:?}", err))
+            } else {
+                std::fs::write(Self::FILE_PATH, self.to_save_string()).map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, level: u32) -> HighScoreEntry {
+        HighScoreEntry { score, level, best_combo: 0 }
+    }
+
+    #[test]
+    fn replacing_keeps_one_entry_per_run() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry(500, 1));
+        let rank = high_scores.replace(None, entry(300, 1));
+        assert_eq!(rank, Some(1));
+        let rank = high_scores.replace(rank, entry(800, 2));
+        assert_eq!(rank, Some(0));
+        assert_eq!(high_scores.entries, vec![entry(800, 2), entry(500, 1)]);
+    }
+}
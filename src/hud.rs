@@ -0,0 +1,166 @@
+use cgmath::{Vector2, Vector4};
+
+use crate::{world::World, util::format_time, ui::{DrawCommand, Rect}, graphics::text::{FontMetricsInfo, FontInfoContainer, BaseFontInfoContainer}};
+
+// the hud is laid out for a window this many logical pixels tall and scaled from there
+const REFERENCE_HEIGHT: f32 = 720.0;
+const MARGIN: f32 = 12.0;
+const BAR_WIDTH: f32 = 200.0;
+const BAR_HEIGHT: f32 = 8.0;
+const HEART_SIZE: f32 = 18.0;
+
+const TEXT_COLOR: Vector4<f32> = Vector4::new(1.0, 1.0, 1.0, 1.0);
+const BAR_BACKGROUND: Vector4<f32> = Vector4::new(0.0, 0.0, 0.0, 0.6);
+const HEART_COLOR: Vector4<f32> = Vector4::new(0.9, 0.15, 0.2, 1.0);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+// keeps the hud the same logical size on high dpi screens, growing it a bit for large windows
+pub fn hud_scale(window_size: Vector2<f32>, scale_factor: f64) -> f32 {
+    let scale_factor = scale_factor as f32;
+    let logical_height = window_size.y / scale_factor;
+    scale_factor * f32::clamp(logical_height / REFERENCE_HEIGHT, 0.75, 2.0)
+}
+
+// top left corner of a box of size placed in the anchor's corner of the window
+pub fn anchor_position(anchor: Anchor, size: Vector2<f32>, window_size: Vector2<f32>, margin: f32) -> Vector2<f32> {
+    match anchor {
+        Anchor::TopLeft => Vector2::new(margin, margin),
+        Anchor::TopRight => Vector2::new(window_size.x - margin - size.x, margin),
+        Anchor::BottomLeft => Vector2::new(margin, window_size.y - margin - size.y),
+        Anchor::BottomRight => Vector2::new(window_size.x - margin - size.x, window_size.y - margin - size.y),
+    }
+}
+
+struct HudBuilder<'a> {
+    font: &'a FontMetricsInfo,
+    window_size: Vector2<f32>,
+    scale: f32,
+    commands: Vec<DrawCommand>,
+}
+
+impl<'a> HudBuilder<'a> {
+    fn line_height(&self) -> f32 {
+        self.font.line_height() * self.scale
+    }
+
+    fn text_size(&self, text: &str) -> Vector2<f32> {
+        Vector2::new(self.font.text_width(text) * self.scale, self.line_height())
+    }
+
+    // lines of text stacked in the anchor's corner, right aligned on the right side of the window
+    fn text_block(&mut self, anchor: Anchor, lines: &[String]) {
+        let line_height = self.line_height();
+        let width = lines.iter().map(|line| self.text_size(line).x).fold(0.0, f32::max);
+        let size = Vector2::new(width, line_height * lines.len() as f32);
+        let top_left = anchor_position(anchor, size, self.window_size, MARGIN * self.scale);
+        for (i, line) in lines.iter().enumerate() {
+            let x = match anchor {
+                Anchor::TopRight | Anchor::BottomRight => top_left.x + width - self.text_size(line).x,
+                Anchor::TopLeft | Anchor::BottomLeft => top_left.x,
+            };
+            // text is positioned by its baseline
+            let y = top_left.y + line_height * (i as f32 + 0.8);
+            self.commands.push(DrawCommand::Text {
+                text: line.clone(),
+                position: Vector2::new(x, y),
+                color: TEXT_COLOR,
+                scale: self.scale,
+            });
+        }
+    }
+
+    fn rect(&mut self, min: Vector2<f32>, size: Vector2<f32>, color: Vector4<f32>) {
+        self.commands.push(DrawCommand::Rect { rect: Rect::new(min, size), color });
+    }
+
+    // background bar with fraction of it filled in color
+    fn bar(&mut self, min: Vector2<f32>, fraction: f32, color: Vector4<f32>) {
+        let size = Vector2::new(BAR_WIDTH, BAR_HEIGHT) * self.scale;
+        self.rect(min, size, BAR_BACKGROUND);
+        self.rect(min, Vector2::new(size.x * f32::clamp(fraction, 0.0, 1.0), size.y), color);
+    }
+}
+
+// draw commands for the in-game hud, read from the world every frame
+pub fn build_hud(world: &World, font: &FontMetricsInfo, window_size: Vector2<f32>, scale: f32) -> Vec<DrawCommand> {
+    let mut hud = HudBuilder { font, window_size, scale, commands: vec![] };
+    let margin = MARGIN * scale;
+
+    // score in the top left
+    hud.text_block(Anchor::TopLeft, &[
+        if world.score.combo > 1 {
+            format!("Score {}  combo x{}", world.score.score, world.score.combo)
+        } else {
+            format!("Score {}", world.score.score)
+        },
+    ]);
+
+    // the waves are the levels, each one ends after kills_to_complete kills. level and timer in the top right
+    hud.text_block(Anchor::TopRight, &[
+        format!("Level {}  {}/{}", world.level, world.level_kills(), world.kills_to_complete()),
+        format_time(world.elapsed),
+    ]);
+
+    // active spell with its cooldown and the player's mana in the bottom left
+    let spellbook = &world.player.spellbook;
+    let bars_height = BAR_HEIGHT * scale * 2.0 + margin;
+    let spell_text = format!("[{}] {}", spellbook.current + 1, spellbook.current_spell().name);
+    let text_size = hud.text_size(&spell_text);
+    let block_size = Vector2::new(f32::max(text_size.x, BAR_WIDTH * scale), text_size.y + bars_height);
+    let top_left = anchor_position(Anchor::BottomLeft, block_size, window_size, margin);
+    hud.commands.push(DrawCommand::Text {
+        text: spell_text,
+        position: Vector2::new(top_left.x, top_left.y + text_size.y * 0.8),
+        color: TEXT_COLOR,
+        scale,
+    });
+    let bar_y = top_left.y + text_size.y + margin / 2.0;
+    hud.bar(Vector2::new(top_left.x, bar_y), 1.0 - spellbook.cooldown_fraction(), Vector4::new(1.0, 0.8, 0.3, 1.0));
+    hud.bar(Vector2::new(top_left.x, bar_y + BAR_HEIGHT * scale * 1.5),
+        spellbook.mana / spellbook.max_mana, Vector4::new(0.3, 0.5, 1.0, 1.0));
+
+    // enemies kill on contact so the player has one life, a heart in the bottom right grayed out once it's lost
+    let heart = Vector2::new(HEART_SIZE, HEART_SIZE) * scale;
+    let top_left = anchor_position(Anchor::BottomRight, heart, window_size, margin);
+    hud.rect(top_left, heart, if world.player.alive { HEART_COLOR } else { BAR_BACKGROUND });
+
+    hud.commands
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::world::{stage::StageLayout, spell::default_spells};
+
+    use super::*;
+
+    // the color of the heart, the last rect of the hud
+    fn heart_color(world: &World) -> Vector4<f32> {
+        let font = FontMetricsInfo { char_data: HashMap::new(), font_size: 20.0, height: 20.0 };
+        let window_size = Vector2::new(800.0, 600.0);
+        let commands = build_hud(world, &font, window_size, 1.0);
+        match commands.last() {
+            Some(DrawCommand::Rect { rect, color }) => {
+                assert_eq!(rect.min + rect.size, window_size - Vector2::new(MARGIN, MARGIN));
+                *color
+            },
+            _ => panic!("the heart should be drawn last"),
+        }
+    }
+
+    #[test]
+    fn heart_shows_whether_the_player_is_alive() {
+        let mut world = World::new(&StageLayout::default(), &default_spells());
+        assert_eq!(heart_color(&world), HEART_COLOR);
+        world.player.alive = false;
+        assert_eq!(heart_color(&world), BAR_BACKGROUND);
+    }
+}
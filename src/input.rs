@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+
+use winit::event::{VirtualKeyCode, MouseButton};
+
+use crate::{State, chatbox::MessageCategory, console::{CommandRegistry, Args, ArgType, arg}};
+
+// what the game cares about, independent of which key or button triggers it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Jump,
+    Dash,
+    Fire,
+    NextSpell,
+    PrevSpell,
+    Spell1,
+    Spell2,
+    Spell3,
+    Spell4,
+    Spell5,
+    Spell6,
+    Reset,
+    Pause,
+}
+
+impl Action {
+    pub fn all() -> Vec<Action> {
+        use Action::*;
+        vec![MoveLeft, MoveRight, MoveUp, MoveDown, Jump, Dash, Fire, NextSpell, PrevSpell,
+             Spell1, Spell2, Spell3, Spell4, Spell5, Spell6, Reset, Pause]
+    }
+
+    // the spell selection actions in spellbook order
+    pub fn spells() -> [Action; 6] {
+        use Action::*;
+        [Spell1, Spell2, Spell3, Spell4, Spell5, Spell6]
+    }
+
+    pub fn name(&self) -> &'static str {
+        use Action::*;
+        match *self {
+            MoveLeft => "move_left",
+            MoveRight => "move_right",
+            MoveUp => "move_up",
+            MoveDown => "move_down",
+            Jump => "jump",
+            Dash => "dash",
+            Fire => "fire",
+            NextSpell => "next_spell",
+            PrevSpell => "prev_spell",
+            Spell1 => "spell1",
+            Spell2 => "spell2",
+            Spell3 => "spell3",
+            Spell4 => "spell4",
+            Spell5 => "spell5",
+            Spell6 => "spell6",
+            Reset => "reset",
+            Pause => "pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::all().into_iter().find(|action| action.name() == name)
+    }
+}
+
+// gamepad inputs we can bind, named by position rather than by the label on any one controller
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Start,
+    Select,
+    // left stick pushed past a threshold, so it can drive digital actions
+    StickLeft,
+    StickRight,
+    StickUp,
+    StickDown,
+}
+
+impl GamepadButton {
+    pub fn all() -> Vec<GamepadButton> {
+        use GamepadButton::*;
+        vec![South, East, West, North, LeftShoulder, RightShoulder, LeftTrigger, RightTrigger,
+             DPadUp, DPadDown, DPadLeft, DPadRight, Start, Select, StickLeft, StickRight, StickUp, StickDown]
+    }
+
+    pub fn name(&self) -> &'static str {
+        use GamepadButton::*;
+        match *self {
+            South => "pad_south",
+            East => "pad_east",
+            West => "pad_west",
+            North => "pad_north",
+            LeftShoulder => "pad_lb",
+            RightShoulder => "pad_rb",
+            LeftTrigger => "pad_lt",
+            RightTrigger => "pad_rt",
+            DPadUp => "pad_up",
+            DPadDown => "pad_down",
+            DPadLeft => "pad_left",
+            DPadRight => "pad_right",
+            Start => "pad_start",
+            Select => "pad_select",
+            StickLeft => "stick_left",
+            StickRight => "stick_right",
+            StickUp => "stick_up",
+            StickDown => "stick_down",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+// keys that can be bound, Return is left out since it always opens the chatbox
+const BINDABLE_KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Up, Down, Left, Right,
+        Space, Tab, Escape, Back, Delete, Insert, Home, End, PageUp, PageDown,
+        LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Comma, Period, Slash, Semicolon, Apostrophe, LBracket, RBracket, Backslash, Minus, Equals, Grave,
+    ]
+};
+
+const MOUSE_BUTTONS: &[(MouseButton, &str)] = &[
+    (MouseButton::Left, "mouse_left"),
+    (MouseButton::Right, "mouse_right"),
+    (MouseButton::Middle, "mouse_middle"),
+];
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key).to_lowercase(),
+            Binding::Mouse(button) => MOUSE_BUTTONS.iter()
+                .find(|(b, _)| b == button)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format!("{:?}", button).to_lowercase()),
+            Binding::Gamepad(button) => button.name().to_string(),
+        }
+    }
+
+    pub fn all() -> Vec<Binding> {
+        BINDABLE_KEYS.iter().map(|key| Binding::Key(*key))
+            .chain(MOUSE_BUTTONS.iter().map(|(button, _)| Binding::Mouse(*button)))
+            .chain(GamepadButton::all().into_iter().map(Binding::Gamepad))
+            .collect()
+    }
+
+    pub fn parse(name: &str) -> Option<Binding> {
+        let name = name.to_lowercase();
+        Binding::all().into_iter().find(|binding| binding.name() == name)
+    }
+
+    fn same_device(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    map: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Action::*;
+        use Binding::*;
+        use VirtualKeyCode as K;
+        use GamepadButton as G;
+        let map = vec![
+            (MoveLeft, vec![Key(K::A), Gamepad(G::DPadLeft), Gamepad(G::StickLeft)]),
+            (MoveRight, vec![Key(K::D), Gamepad(G::DPadRight), Gamepad(G::StickRight)]),
+            (MoveUp, vec![Key(K::W), Gamepad(G::DPadUp), Gamepad(G::StickUp)]),
+            (MoveDown, vec![Key(K::S), Gamepad(G::DPadDown), Gamepad(G::StickDown)]),
+            (Jump, vec![Key(K::Space), Gamepad(G::South)]),
+            (Dash, vec![Key(K::E), Gamepad(G::East), Gamepad(G::LeftTrigger)]),
+            (Fire, vec![Mouse(MouseButton::Left), Gamepad(G::RightTrigger)]),
+            (NextSpell, vec![Gamepad(G::RightShoulder)]),
+            (PrevSpell, vec![Gamepad(G::LeftShoulder)]),
+            (Spell1, vec![Key(K::Key1)]),
+            (Spell2, vec![Key(K::Key2)]),
+            (Spell3, vec![Key(K::Key3)]),
+            (Spell4, vec![Key(K::Key4)]),
+            (Spell5, vec![Key(K::Key5)]),
+            (Spell6, vec![Key(K::Key6)]),
+            (Reset, vec![Key(K::R), Gamepad(G::Select)]),
+            (Pause, vec![Key(K::Escape), Gamepad(G::Start)]),
+        ].into_iter().collect();
+        Self { map }
+    }
+}
+
+impl Bindings {
+    pub const CONFIG_PATH: &'static str = "bindings.cfg";
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.map.get(&action).map(|b| b.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn actions_for(&self, binding: Binding) -> Vec<Action> {
+        Action::all().into_iter().filter(|action| self.get(*action).contains(&binding)).collect()
+    }
+
+    // replaces the action's bindings on the same device, so rebinding a key keeps mouse and gamepad bindings
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.map.entry(action).or_default();
+        bindings.retain(|b| !b.same_device(&binding));
+        bindings.push(binding);
+    }
+
+    pub fn unbind(&mut self, action: Action) {
+        self.map.remove(&action);
+    }
+
+    // one "bind <action> <binding>" line per binding, the same syntax as the console command
+    pub fn to_config_string(&self) -> String {
+        Action::all().into_iter()
+            .flat_map(|action| self.get(action).iter()
+                .map(move |binding| format!("bind {} {}\n", action.name(), binding.name())))
+            .collect()
+    }
+
+    // starts from no bindings at all, blank lines and lines starting with # are skipped
+    pub fn from_config_str(config: &str) -> Result<Bindings, String> {
+        let mut bindings = Bindings { map: HashMap::new() };
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["bind", action, binding] => {
+                    let (action, binding) = parse_bind(action, binding)
+                        .map_err(|err| format!("line {}: {}", number + 1, err))?;
+                    bindings.map.entry(action).or_default().push(binding);
+                },
+                _ => return Err(format!("line {}: expected bind <action> <binding>", number + 1)),
+            }
+        }
+        Ok(bindings)
+    }
+
+    // falls back to the defaults if there is no config file or it can't be read
+    pub fn load() -> Bindings {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Bindings::default()
+            } else {
+                match std::fs::read_to_string(Self::CONFIG_PATH) {
+                    Ok(config) => Bindings::from_config_str(&config).unwrap_or_else(|err| {
+                        log::warn!("Couldn't parse {}: {}", Self::CONFIG_PATH, err);
+                        Bindings::default()
+                    }),
+                    Err(_) => Bindings::default(),
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Err("saving bindings is not supported on web".to_string())
+            } else {
+                std::fs::write(Self::CONFIG_PATH, self.to_config_string()).map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+pub fn parse_bind(action: &str, binding: &str) -> Result<(Action, Binding), String> {
+    let action = Action::from_name(action).ok_or_else(|| format!("unknown action {}", action))?;
+    let binding = Binding::parse(binding).ok_or_else(|| format!("unknown key or button {}", binding))?;
+    Ok((action, binding))
+}
+
+// which actions are held and which changed this frame
+#[derive(Default)]
+pub struct ActionState {
+    pub down: HashSet<Action>,
+    pub pos_edge: HashSet<Action>,
+    pub neg_edge: HashSet<Action>,
+    bindings_down: HashSet<Binding>,
+}
+
+impl ActionState {
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pos_edge.contains(&action)
+    }
+
+    // an action stays down while any of its bindings are down, returns whether the binding maps to any action
+    pub fn handle(&mut self, bindings: &Bindings, binding: Binding, pressed: bool) -> bool {
+        if pressed {
+            self.bindings_down.insert(binding);
+        } else {
+            self.bindings_down.remove(&binding);
+        }
+        let actions = bindings.actions_for(binding);
+        for action in actions.iter() {
+            let down = bindings.get(*action).iter().any(|b| self.bindings_down.contains(b));
+            match (down, self.down.contains(action)) {
+                (true, false) => {
+                    self.down.insert(*action);
+                    self.pos_edge.insert(*action);
+                },
+                (false, true) => {
+                    self.down.remove(action);
+                    self.neg_edge.insert(*action);
+                },
+                _ => (),
+            }
+        }
+        !actions.is_empty()
+    }
+
+    pub fn clear_edges(&mut self) {
+        self.pos_edge.clear();
+        self.neg_edge.clear();
+    }
+}
+
+fn action_names() -> Vec<String> {
+    Action::all().iter().map(|action| action.name().to_string()).collect()
+}
+
+fn binding_names() -> Vec<String> {
+    Binding::all().iter().map(|binding| binding.name()).collect()
+}
+
+fn save_bindings(state: &mut State) -> Result<(), String> {
+    state.bindings.save().map_err(|err| format!("Couldn't save bindings: {}", err))
+}
+
+pub fn register_commands(registry: &mut CommandRegistry<State>) {
+    registry.register("bind",
+        vec![arg("action", ArgType::Choice(action_names)), arg("binding", ArgType::Choice(binding_names))],
+        "binds a key, mouse or gamepad button to an action, replacing the action's binding on that device",
+        |state: &mut State, args: &Args| {
+            let (action, binding) = parse_bind(args.word(0), args.word(1))?;
+            state.bindings.bind(action, binding);
+            save_bindings(state)
+        });
+    registry.register("unbind",
+        vec![arg("action", ArgType::Choice(action_names))],
+        "removes every binding of an action",
+        |state: &mut State, args: &Args| {
+            let action = Action::from_name(args.word(0)).ok_or_else(|| format!("unknown action {}", args.word(0)))?;
+            state.bindings.unbind(action);
+            save_bindings(state)
+        });
+    registry.register("bindings", vec![], "lists the bindings of every action",
+        |state: &mut State, _: &Args| {
+            for action in Action::all() {
+                let names: Vec<String> = state.bindings.get(action).iter().map(|b| b.name()).collect();
+                state.chatbox.print(MessageCategory::Command, &format!("{}: {}", action.name(), names.join(" ")));
+            }
+            Ok(())
+        });
+}
@@ -1,8 +1,17 @@
 #![windows_subsystem = "windows"]
+use assets::{Assets, AssetWatcher, Reloaded};
+use animation::Animations;
 use audio::Audio;
-use cgmath::{Vector2, Zero, Point2, EuclideanSpace, Vector4};
-use chatbox::Chatbox;
-use graphics::{RenderEngine, text::BaseFontInfoContainer};
+use cgmath::{Vector2, Zero, Point2, EuclideanSpace};
+use chatbox::{Chatbox, MessageCategory};
+use gamepad::Gamepad;
+use game_state::{GameState, GameStateChange, GameStateStack};
+use graphics::{RenderEngine, parallax::Backgrounds, text::{BaseFontInfoContainer, FontMetricsInfo}};
+use ui::{Ui, UiInput, UiState, DrawCommand};
+use highscore::{HighScores, HighScoreEntry};
+use console::{CommandRegistry, CommandHistory, Args, ArgType, arg, optional};
+use tuning::{Tuning, TuningWatcher};
+use clipboard::Clipboard;
 use instant::Instant;
 use std::{collections::HashSet, rc::Rc};
 
@@ -16,9 +25,9 @@ use winit::window::Window;
 #[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
 
-use world::World;
+use world::{World, stage::StageLayout, spell::{self, SpellDef}};
 
-use crate::{world::stage, graphics::ResolveInstance};
+use crate::{input::{Action, ActionState, Binding, Bindings}};
 
 mod bounding_box;
 mod camera;
@@ -27,6 +36,19 @@ mod world;
 pub mod util;
 pub mod chatbox;
 pub mod audio;
+pub mod input;
+pub mod gamepad;
+pub mod game_state;
+pub mod ui;
+pub mod hud;
+pub mod highscore;
+pub mod console;
+pub mod tuning;
+pub mod line_editor;
+pub mod clipboard;
+pub mod logger;
+pub mod assets;
+pub mod animation;
 
 #[cfg_attr(target_arch="wasm32", wasm_bindgen(start))]
 pub async fn run() {
@@ -34,11 +56,9 @@ pub async fn run() {
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-            console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
-        } else {
-            env_logger::init();
         }
     }
+    logger::init().expect("Couldn't initialize logger");
 
     let event_loop = EventLoop::new();
     let window = Rc::new(WindowBuilder::new().build(&event_loop).unwrap());
@@ -103,7 +123,8 @@ pub async fn run() {
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
                     }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    WindowEvent::ScaleFactorChanged { new_inner_size, scale_factor } => {
+                        state.scale_factor = *scale_factor;
                         state.resize(**new_inner_size);
                     }
                     _ => {}
@@ -141,32 +162,50 @@ pub enum FocusMode {
     Default, Chatbox
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
-pub enum GameState {
-    Game, Editor
-}
-
 pub struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
+    scale_factor: f64,
     render_engine: RenderEngine,
 
     camera: camera::Camera,
     camera_controller: camera::CameraController,
+    camera_follow: camera::CameraFollow,
 
     last_frame: Instant,
 
     pub world: World,
+    // the tiles every level starts with
+    stage_layout: StageLayout,
+    spells: Vec<SpellDef>,
     pub input_state: InputState,
+    pub bindings: Bindings,
+    pub gamepad: Gamepad,
     pub mouse_pos_view: Vector2<f32>,
 
     pub chatbox: Chatbox,
     pub focus_mode: FocusMode,
-    pub game_state: GameState,
-
+    pub game_states: GameStateStack,
+    pub ui_state: UiState,
+    pub high_scores: HighScores,
+    pub clipboard: Clipboard,
+    pub tuning: Tuning,
+    tuning_watcher: TuningWatcher,
+    asset_watcher: AssetWatcher,
+    commands: Rc<CommandRegistry<State>>,
+    command_history: CommandHistory,
+    exit_requested: bool,
+    // multiplies the time the world is updated by each frame
+    pub time_scale: f32,
+    // where the current or last run placed in high_scores, its entry is replaced as the run goes on
+    last_rank: Option<usize>,
+    ui_font: FontMetricsInfo,
+    ui_commands: Vec<DrawCommand>,
+
+    pub assets: Assets,
     pub audio: Audio,
 }
 
@@ -175,7 +214,17 @@ pub struct InputState {
     pub key_pos_edge: HashSet<VirtualKeyCode>,
     pub key_neg_edge: HashSet<VirtualKeyCode>,
     pub mouse_pos_edge: HashSet<MouseButton>,
+    pub mouse_down: HashSet<MouseButton>,
     pub mouse_position: Vector2<f32>,
+    // scroll lines this frame, positive is away from the user
+    pub mouse_wheel: f32,
+    // gamepad sticks with deadzones applied, y points down like world coordinates
+    pub left_stick: Vector2<f32>,
+    pub right_stick: Vector2<f32>,
+    // direction to aim in when the right stick was used more recently than the mouse
+    pub aim_stick: Option<Vector2<f32>>,
+    pub actions: ActionState,
+    pub modifiers: ModifiersState,
     pub commands: Vec<String>,
     pub edit: bool,
 }
@@ -236,11 +285,19 @@ impl State {
         // camera
         let camera = camera::Camera::new(cgmath::Vector2::new(size.width, size.height), 20.0);
         let camera_controller = camera::CameraController::new(1.0);
-
-        let render_engine = RenderEngine::init(&device, &queue, &config);
-        let chatbox = Chatbox::new(render_engine.font.get_metrics_info(), 7, 38.0, 7, 800.0);
-
-        let mut audio = Audio::new();
+        let camera_follow = camera::CameraFollow::new();
+
+        // {:#} prints the whole chain, e.g. which texture failed and why
+        let mut assets = Assets::new(&device, &queue)
+            .unwrap_or_else(|err| panic!("Couldn't load assets: {:#}", err));
+        let stage_layout = StageLayout::load(&mut assets);
+        let spells = spell::load_spells(&mut assets);
+        let render_engine = RenderEngine::init(&device, &queue, &config, &mut assets)
+            .unwrap_or_else(|err| panic!("Couldn't load assets: {:#}", err));
+        let chatbox = Chatbox::new(assets.font(render_engine.font).get_metrics_info(), 7, 38.0, 500, 800.0);
+        let ui_font = assets.font(render_engine.font).get_metrics_info();
+
+        let mut audio = Audio::new(&mut assets).unwrap_or_else(|err| panic!("Couldn't load assets: {:#}", err));
         #[cfg(not(target_arch = "wasm32"))]
         audio.init_audio();
         audio.play(audio::Song::Church);
@@ -251,28 +308,116 @@ impl State {
             queue,
             config,
             size,
+            scale_factor: window.scale_factor(),
             render_engine,
             camera,
             camera_controller,
+            camera_follow,
             last_frame: Instant::now(),
-            world: World::new(),
+            world: World::new(&stage_layout, &spells),
+            stage_layout,
+            spells,
             input_state: InputState {
                 key_down: HashSet::new(),
                 key_pos_edge: HashSet::new(),
                 key_neg_edge: HashSet::new(),
                 mouse_pos_edge: HashSet::new(),
+                mouse_down: HashSet::new(),
                 mouse_position: Vector2::zero(),
+                mouse_wheel: 0.0,
+                left_stick: Vector2::zero(),
+                right_stick: Vector2::zero(),
+                aim_stick: None,
+                actions: ActionState::default(),
+                modifiers: ModifiersState::empty(),
                 commands: vec![],
                 edit: true,
             },
+            bindings: Bindings::load(),
+            gamepad: Gamepad::new(),
             mouse_pos_view: Vector2::zero(),
             chatbox,
             focus_mode: FocusMode::Default,
-            game_state: GameState::Game,
+            game_states: GameStateStack::new(GameState::Title),
+            ui_state: UiState::default(),
+            high_scores: HighScores::load(),
+            clipboard: Clipboard::new(),
+            tuning: Tuning::load(),
+            tuning_watcher: TuningWatcher::new(),
+            asset_watcher: AssetWatcher::new(),
+            commands: Rc::new(State::command_registry()),
+            command_history: CommandHistory::new(50),
+            exit_requested: false,
+            time_scale: 1.0,
+            last_rank: None,
+            ui_font,
+            ui_commands: vec![],
+            assets,
             audio,
         }
     }
 
+    fn command_registry() -> CommandRegistry<State> {
+        let mut registry = CommandRegistry::new();
+        registry.register("help", vec![optional("command", ArgType::Word)], "lists commands, or shows how to use one",
+            |state: &mut State, args: &Args| {
+                let commands = state.commands.clone();
+                if args.is_empty() {
+                    let names: Vec<&str> = commands.commands().map(|command| command.name).collect();
+                    state.chatbox.print(MessageCategory::Command, &names.join(" "));
+                    return Ok(())
+                }
+                let command = commands.get(args.word(0)).ok_or_else(|| format!("Unknown command {}", args.word(0)))?;
+                state.chatbox.print(MessageCategory::Command, &command.usage());
+                state.chatbox.print(MessageCategory::Command, command.help);
+                Ok(())
+            });
+        registry.register("exit", vec![], "quits the game", |state: &mut State, _: &Args| {
+            state.exit_requested = true;
+            Ok(())
+        });
+        registry.register("edit", vec![], "opens the level editor", |state: &mut State, _: &Args| {
+            state.change_game_state(GameStateChange::EnterEditor);
+            Ok(())
+        });
+        registry.register("game", vec![], "closes the level editor", |state: &mut State, _: &Args| {
+            state.change_game_state(GameStateChange::ExitEditor);
+            Ok(())
+        });
+        registry.register("timescale", vec![arg("scale", ArgType::Float)], "speeds up or slows down the game, 1 is normal",
+            |state: &mut State, args: &Args| {
+                let scale = args.float(0);
+                if scale < 0.0 {
+                    return Err("timescale can't be negative".to_string())
+                }
+                state.time_scale = scale;
+                Ok(())
+            });
+        registry.register("pixelperfect", vec![optional("enabled", ArgType::Bool)],
+            "draws the world at a low resolution scaled up by a whole number, toggles without an argument",
+            |state: &mut State, args: &Args| {
+                let camera = &mut state.camera;
+                camera.pixel_perfect = if args.len() == 1 { args.bool(0) } else { !camera.pixel_perfect };
+                let message = format!("pixel perfect {}", if camera.pixel_perfect { "on" } else { "off" });
+                state.chatbox.print(MessageCategory::Command, &message);
+                Ok(())
+            });
+        registry.register("logfilter", vec![optional("category", ArgType::Choice(log_filter_names))],
+            "shows only one category of messages in the chatbox, or all of them without an argument",
+            |state: &mut State, args: &Args| {
+                let filter = match args.len() {
+                    0 => MessageCategory::all(),
+                    _ => MessageCategory::from_name(args.word(0)).into_iter().collect(),
+                };
+                state.chatbox.set_filter(filter);
+                Ok(())
+            });
+        input::register_commands(&mut registry);
+        world::register_commands(&mut registry);
+        tuning::register_commands(&mut registry);
+        registry
+    }
+
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
@@ -284,6 +429,9 @@ impl State {
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::ModifiersChanged(modifiers) = *event {
+            self.input_state.modifiers = modifiers;
+        }
         if self.focus_mode == FocusMode::Chatbox {
             match *event {
                 WindowEvent::KeyboardInput {
@@ -305,153 +453,382 @@ impl State {
                                 self.focus_mode = FocusMode::Default;
                                 self.chatbox.set_typing_flicker(false);
                             } else {
-                                let typing = self.chatbox.get_typing().clone();
-                                self.chatbox.println(&typing);
+                                let typing = self.chatbox.get_typing().to_string();
+                                self.chatbox.print(MessageCategory::Chat, &typing);
                                 self.chatbox.erase_typing();
                                 self.focus_mode = FocusMode::Default;
                                 self.chatbox.set_typing_flicker(false);
+                                self.command_history.push(&typing);
                                 self.input_state.commands.push(typing);
                             }
                         },
-                        _ => {
-                        }
+                        VirtualKeyCode::Up => {
+                            if let Some(line) = self.command_history.up() {
+                                let line = line.to_string();
+                                self.chatbox.set_typing(&line);
+                            }
+                        },
+                        VirtualKeyCode::Down => {
+                            if let Some(line) = self.command_history.down() {
+                                let line = line.to_string();
+                                self.chatbox.set_typing(&line);
+                            }
+                        },
+                        VirtualKeyCode::Tab => self.complete_typing(),
+                        VirtualKeyCode::PageUp => self.chatbox.scroll_page(1),
+                        VirtualKeyCode::PageDown => self.chatbox.scroll_page(-1),
+                        key => self.edit_typing(key),
                     }
                     return true
                 },
+                WindowEvent::MouseWheel { delta, .. } => {
+                    self.chatbox.scroll(match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y.round() as i32,
+                        MouseScrollDelta::PixelDelta(position) => (position.y / 20.0).round() as i32,
+                    });
+                    return true
+                },
                 WindowEvent::ReceivedCharacter(c) => {
-                    if c == '\x08' { // backspace
-                        self.chatbox.remove_typing(1);
-                    } else if c == '\n' || c == '\r' || c.to_string().as_bytes()[0] == 13 {
-                        // ahhhhhhhhhhh why can't we capture new lines?
-                    } else {
-                        self.chatbox.add_typing(c);
+                    // backspace, enter, tab and ctrl shortcuts arrive as control characters,
+                    // they are handled with the key press instead
+                    if !c.is_control() {
+                        self.chatbox.typing_editor().insert(c.encode_utf8(&mut [0; 4]));
                     }
                 },
                 _ => ()
             };
         }
-        let relevant_inputs = {
-            use VirtualKeyCode::*;
-            vec![Key1, Key2, A, S, D, W, E, Space, LShift]
-        };
-        if !self.camera_controller.process_events(event) {
-            match *event {
-                WindowEvent::KeyboardInput {
-                    input: KeyboardInput {
-                        state,
-                        virtual_keycode:
-                        Some(key),
-                        ..
-                    },
+        match *event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state,
+                    virtual_keycode:
+                    Some(key),
                     ..
-                } => match key {
-                    VirtualKeyCode::Return => {
-                        self.focus_mode = FocusMode::Chatbox;
-                        self.chatbox.set_typing_flicker(true);
-                        true
-                    },
-                    VirtualKeyCode::R => {
-                        self.world = World::new();
-                        true
-                    },
-                    _ => {
-                        if relevant_inputs.contains(&key) {
-                            match state {
-                                ElementState::Pressed => {
-                                    self.input_state.key_down.insert(key);
-                                    self.input_state.key_pos_edge.insert(key);
-                                },
-                                ElementState::Released => {
-                                    self.input_state.key_down.remove(&key);
-                                    self.input_state.key_neg_edge.insert(key);
-                                },
-                            };
-                            true
-                        } else { false }
-                    }
                 },
-                WindowEvent::CursorMoved { position, .. } => {
-                    let pos = Point2::new(position.x as f32, position.y as f32);
-                    self.mouse_pos_view = pos.to_vec();
-                    self.input_state.mouse_position = self.camera.view_to_world_pos(pos).to_vec();
+                ..
+            } => match key {
+                VirtualKeyCode::Return => {
+                    self.focus_mode = FocusMode::Chatbox;
+                    self.chatbox.set_typing_flicker(true);
                     true
                 },
-                WindowEvent::MouseInput {
-                    state: ElementState::Pressed,
-                    button,
-                    ..
-                } => {
-                    self.audio.init_audio();
-                    self.input_state.mouse_pos_edge.insert(button);
-                    true
-                },
-                _ => false,
-            }
-        } else {
-            true
+                _ => {
+                    match state {
+                        ElementState::Pressed => {
+                            self.input_state.key_down.insert(key);
+                            self.input_state.key_pos_edge.insert(key);
+                        },
+                        ElementState::Released => {
+                            self.input_state.key_down.remove(&key);
+                            self.input_state.key_neg_edge.insert(key);
+                        },
+                    };
+                    self.input_state.actions.handle(&self.bindings, Binding::Key(key), state == ElementState::Pressed)
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let pos = Point2::new(position.x as f32, position.y as f32);
+                self.mouse_pos_view = pos.to_vec();
+                self.input_state.mouse_position = self.camera.view_to_world_pos(pos).to_vec();
+                self.input_state.aim_stick = None;
+                true
+            },
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button,
+                ..
+            } => {
+                self.audio.init_audio();
+                self.input_state.mouse_pos_edge.insert(button);
+                self.input_state.mouse_down.insert(button);
+                self.input_state.actions.handle(&self.bindings, Binding::Mouse(button), true);
+                true
+            },
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button,
+                ..
+            } => {
+                self.input_state.mouse_down.remove(&button);
+                self.input_state.actions.handle(&self.bindings, Binding::Mouse(button), false);
+                true
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.input_state.mouse_wheel += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    // roughly one line per 20 pixels
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+                true
+            },
+            _ => false,
         }
     }
 
     fn update(&mut self) -> bool {
-        if self.input_state.commands.iter().map(|command| {
-            match command.split(' ').collect::<Vec<_>>()[..] {
-                ["exit"] => return true,
-                ["edit"] => self.game_state = GameState::Editor,
-                ["game"] => self.game_state = GameState::Game,
-                _ => self.chatbox.println("Unknown command"),
-            }
-            return false;
-        }).find(|x| *x).is_some() {
+        self.gamepad.poll(&mut self.input_state, &self.bindings);
+
+        let commands = std::mem::take(&mut self.input_state.commands);
+        for command in commands {
+            self.run_command(&command);
+        }
+        if self.exit_requested {
             return true
         }
-        self.input_state.commands.clear();
 
         // timing
         let frame = Instant::now();
-        let delta_time = ((frame - self.last_frame).as_nanos() as f64 / 1000000000.0) as f32;
+        let real_delta_time = ((frame - self.last_frame).as_nanos() as f64 / 1000000000.0) as f32;
         self.last_frame = frame;
 
-        self.audio.update(delta_time);
-
-        if self.game_state == GameState::Editor {
-            // place blocks
-            use stage::TileType::*;
-            let pos = self.input_state.mouse_position;
-            let rounded = Vector2::new((pos.x).floor() as i32, (pos.y).floor() as i32);
-            if self.input_state.mouse_pos_edge.contains(&MouseButton::Left) {
-                self.world.stage.values_mut().next().map(|stage| stage.set_tile(&rounded, Some(Dirt)));
-            }
-            if self.input_state.mouse_pos_edge.contains(&MouseButton::Right) {
-                self.world.stage.values_mut().next().map(|stage| stage.set_tile(&rounded, None));
+        self.audio.update(real_delta_time);
+        for (level, message) in logger::drain() {
+            let category = match level {
+                log::Level::Error => MessageCategory::Error,
+                log::Level::Warn => MessageCategory::Warning,
+                _ => MessageCategory::System,
+            };
+            self.chatbox.print(category, &message);
+        }
+        match self.tuning_watcher.poll(real_delta_time) {
+            Some(Ok(tuning)) => {
+                self.tuning = tuning;
+                self.chatbox.println(&format!("Reloaded {}", Tuning::CONFIG_PATH));
+            },
+            Some(Err(err)) => self.chatbox.print(MessageCategory::Error, &format!("Couldn't reload {}: {}", Tuning::CONFIG_PATH, err)),
+            None => (),
+        }
+        let changed = self.asset_watcher.poll(real_delta_time, &self.assets);
+        for path in &changed {
+            match self.assets.reload(&self.device, &self.queue, path) {
+                Ok(reloaded) => {
+                    for change in reloaded {
+                        match change {
+                            Reloaded::Texture => (),
+                            Reloaded::Replaced(id) => self.render_engine.forget_texture(&id),
+                            Reloaded::Sound(sound) => self.audio.reload(&self.assets, sound),
+                            Reloaded::Data(path) if path == Animations::PATH =>
+                                self.render_engine.animations = Animations::load(&mut self.assets),
+                            Reloaded::Data(path) if path == Backgrounds::PATH =>
+                                self.render_engine.backgrounds = Backgrounds::load(&self.device, &self.queue, &mut self.assets),
+                            Reloaded::Data(path) if path == StageLayout::PATH => {
+                                match self.assets.load_data(&path).map_err(|err| format!("{:#}", err))
+                                    .and_then(|bytes| StageLayout::from_config_str(&String::from_utf8_lossy(&bytes))) {
+                                    Ok(layout) => {
+                                        self.world.set_layout(&layout);
+                                        self.stage_layout = layout;
+                                    },
+                                    Err(err) => self.chatbox.print(MessageCategory::Error, &format!("Couldn't reload {}: {}", path, err)),
+                                }
+                            },
+                            Reloaded::Data(path) if path == spell::PATH => {
+                                match self.assets.load_data(&path).map_err(|err| format!("{:#}", err))
+                                    .and_then(|bytes| spell::spells_from_config_str(&String::from_utf8_lossy(&bytes))) {
+                                    Ok(spells) => {
+                                        self.world.player.spellbook.set_spells(spells.clone());
+                                        self.spells = spells;
+                                    },
+                                    Err(err) => self.chatbox.print(MessageCategory::Error, &format!("Couldn't reload {}: {}", path, err)),
+                                }
+                            },
+                            Reloaded::Data(path) if path == graphics::TILE => {
+                                if let Err(err) = self.render_engine.reload_tileset(&self.device, &self.queue, &mut self.assets) {
+                                    self.chatbox.print(MessageCategory::Error, &format!("Couldn't reload {}: {:#}", path, err));
+                                }
+                            },
+                            Reloaded::Data(_) => (),
+                        }
+                    }
+                    self.chatbox.println(&format!("Reloaded {}", path));
+                },
+                Err(err) => self.chatbox.print(MessageCategory::Error, &format!("Couldn't reload {}: {:#}", path, err)),
             }
-            self.world.debug_objects = vec![
-                ResolveInstance {
-                    overlaps: 0,
-                    color: Vector4::new(1.0, 1.0, 1.0, 1.0),
-                    position: Vector2::new(rounded.x as f32, rounded.y as f32) + Vector2::new(0.5, 0.5),
-                    scale: Vector2::new(1.0, 1.0),
-                }
-            ];
-        } else {
-            // shoot stuff, implemented in world update
         }
-
-        if self.game_state == GameState::Game {
-            self.world.update(delta_time, &self.input_state);
+        // reloading can replace textures and fonts, or load new ones for a changed data file
+        if !changed.is_empty() {
+            self.render_engine.register_assets(&self.device, &self.assets);
+        }
+        let delta_time = real_delta_time * self.time_scale;
+
+        let top = self.game_states.top();
+        let mut changes: Vec<_> = top.handle_input(self)
+            .or_else(|| top.update(self, delta_time, real_delta_time))
+            .into_iter()
+            .collect();
+        // the world under the cursor changes when the camera moves
+        self.input_state.mouse_position = self.camera.view_to_world_pos(Point2::from_vec(self.mouse_pos_view)).to_vec();
+        changes.extend(self.update_ui());
+        for change in changes {
+            self.change_game_state(change);
         }
 
-        // camera update
-        self.camera_controller.update_camera(delta_time, &mut self.camera);
-
-        self.chatbox.update(delta_time);
+        self.chatbox.update(real_delta_time);
         
         // clear inputs
         self.input_state.key_pos_edge.clear();
         self.input_state.key_neg_edge.clear();
         self.input_state.mouse_pos_edge.clear();
+        self.input_state.mouse_wheel = 0.0;
+        self.input_state.actions.clear_edges();
         false
     }
 
+    // follows the player, easing towards zoom
+    fn update_camera(&mut self, delta_time: f32, zoom: f32) {
+        self.camera_follow.add_trauma(std::mem::take(&mut self.world.trauma));
+        self.camera_follow.zoom_to(zoom);
+        let player = &self.world.player.physics;
+        self.camera_follow.update(
+            delta_time,
+            &mut self.camera,
+            Point2::from_vec(player.bounding_box.center),
+            player.velocity,
+            self.world.bounds(),
+            &self.tuning,
+        );
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let result = self.commands.parse(line)
+            .and_then(|(handler, args)| handler(self, &args));
+        if let Err(err) = result {
+            for line in err.lines() {
+                self.chatbox.print(MessageCategory::Error, line);
+            }
+        }
+    }
+
+    // caret movement, deletion and clipboard shortcuts in the chatbox typing line
+    fn edit_typing(&mut self, key: VirtualKeyCode) {
+        let modifiers = self.input_state.modifiers;
+        // ctrl on windows and linux, cmd on mac
+        let shortcut = modifiers.ctrl() || modifiers.logo();
+        let select = modifiers.shift();
+        let editor = self.chatbox.typing_editor();
+        match key {
+            VirtualKeyCode::Left => editor.left(shortcut, select),
+            VirtualKeyCode::Right => editor.right(shortcut, select),
+            VirtualKeyCode::Home => editor.home(select),
+            VirtualKeyCode::End => editor.end(select),
+            VirtualKeyCode::Back => editor.backspace(shortcut),
+            VirtualKeyCode::Delete => editor.delete(shortcut),
+            VirtualKeyCode::A if shortcut => editor.select_all(),
+            VirtualKeyCode::C if shortcut => {
+                if let Some(text) = editor.selected_text() {
+                    let text = text.to_string();
+                    self.clipboard.set(&text);
+                }
+            },
+            VirtualKeyCode::X if shortcut => {
+                if let Some(text) = editor.cut() {
+                    self.clipboard.set(&text);
+                }
+            },
+            VirtualKeyCode::V if shortcut => {
+                let text = self.clipboard.get();
+                self.chatbox.typing_editor().insert(&text);
+            },
+            _ => (),
+        }
+    }
+
+    // completes the word being typed, listing the options if there is more than one
+    fn complete_typing(&mut self) {
+        let typing = self.chatbox.get_typing().to_string();
+        let candidates = self.commands.complete(&typing);
+        if candidates.is_empty() {
+            return
+        }
+        let mut tokens = console::completion_tokens(&typing);
+        tokens.pop();
+        tokens.push(console::common_prefix(&candidates));
+        let mut completed = console::join_tokens(&tokens);
+        if candidates.len() == 1 {
+            completed.push(' ');
+        } else {
+            self.chatbox.print(MessageCategory::Command, &candidates.join("  "));
+        }
+        self.chatbox.set_typing(&completed);
+    }
+
+    // builds this frame's menus, returning any state changes they asked for
+    fn update_ui(&mut self) -> Vec<GameStateChange> {
+        let actions = &self.input_state.actions;
+        let input = UiInput {
+            mouse_position: self.mouse_pos_view,
+            mouse_down: self.input_state.mouse_down.contains(&MouseButton::Left),
+            mouse_pressed: self.input_state.mouse_pos_edge.contains(&MouseButton::Left),
+            focus_next: actions.pressed(Action::MoveDown),
+            focus_prev: actions.pressed(Action::MoveUp),
+            activate: actions.pressed(Action::Jump),
+            adjust: actions.pressed(Action::MoveRight) as i32 - actions.pressed(Action::MoveLeft) as i32,
+        };
+        let origin = Vector2::new(self.size.width as f32 / 2.0 - 100.0, self.size.height as f32 / 2.0 + 80.0);
+        let window_size = Vector2::new(self.size.width as f32, self.size.height as f32);
+        let top = self.game_states.top();
+        let hud_commands = if top.shows_hud() {
+            hud::build_hud(&self.world, &self.ui_font, window_size, hud::hud_scale(window_size, self.scale_factor))
+        } else {
+            vec![]
+        };
+        let mut ui = Ui::new(&mut self.ui_state, input, &self.ui_font, origin);
+        let changes = top.menu(&mut ui, &self.world, &self.high_scores, self.last_rank);
+        self.ui_commands = hud_commands;
+        self.ui_commands.extend(ui.finish());
+        changes
+    }
+
+    fn change_game_state(&mut self, change: GameStateChange) {
+        if !self.game_states.apply(change) {
+            return
+        }
+        match change {
+            GameStateChange::PlayerLose | GameStateChange::LevelComplete => self.record_high_score(),
+            GameStateChange::ToTitle => {
+                // quitting from the pause menu still counts the run
+                self.record_high_score();
+                self.last_rank = None;
+                self.world = World::new(&self.stage_layout, &self.spells);
+            },
+            GameStateChange::Start => {
+                self.last_rank = None;
+                self.world = World::new(&self.stage_layout, &self.spells);
+            },
+            GameStateChange::Restart => {
+                // restarting starts a new run from 0, so the one being left is recorded first.
+                // after a game over it already was, and recording it again keeps the same entry
+                self.record_high_score();
+                self.last_rank = None;
+                self.world = World::with_level(self.world.level, &self.stage_layout, &self.spells);
+            },
+            GameStateChange::NextLevel => {
+                let score = self.world.score.clone();
+                self.world = World::with_level(self.world.level + 1, &self.stage_layout, &self.spells);
+                self.world.carry_over(score);
+            },
+            _ => (),
+        }
+        // a new world starts with the camera on the player instead of sweeping over from the old one
+        if matches!(change, GameStateChange::Start | GameStateChange::ToTitle | GameStateChange::Restart | GameStateChange::NextLevel) {
+            self.camera_follow = camera::CameraFollow::new();
+        }
+    }
+
+    fn record_high_score(&mut self) {
+        let rank = self.high_scores.replace(self.last_rank, HighScoreEntry {
+            score: self.world.score.score,
+            level: self.world.level,
+            best_combo: self.world.score.best_combo,
+        });
+        if rank.is_some() || self.last_rank.is_some() {
+            if let Err(err) = self.high_scores.save() {
+                self.chatbox.print(MessageCategory::Error, &format!("Couldn't save high scores: {}", err));
+            }
+        }
+        self.last_rank = rank;
+    }
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let render_prereq = graphics::RenderPrereq {
             device: &mut self.device,
@@ -459,6 +836,10 @@ impl State {
             surface: &mut self.surface,
             camera: &self.camera
         };
-        self.render_engine.render(render_prereq, &self.chatbox, &self.world)
+        self.render_engine.render(render_prereq, &self.assets, &self.chatbox, &self.world, &self.game_states, &self.ui_commands)
     }
 }
+
+fn log_filter_names() -> Vec<String> {
+    MessageCategory::all().iter().map(|category| category.name().to_string()).collect()
+}
@@ -0,0 +1,306 @@
+// single line text editing with a caret and selection. positions are byte indices into the text
+// that always sit on grapheme cluster boundaries, so multi-byte characters are never split.
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone, Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    caret: usize,
+    // the other end of the selection, which runs from here to the caret
+    anchor: Option<usize>,
+}
+
+// start of the grapheme before i, or 0
+pub fn prev_boundary(text: &str, i: usize) -> usize {
+    text[..i].grapheme_indices(true).next_back().map_or(0, |(start, _)| start)
+}
+
+// end of the grapheme after i, or the end of the text
+pub fn next_boundary(text: &str, i: usize) -> usize {
+    text[i..].graphemes(true).next().map_or(text.len(), |g| i + g.len())
+}
+
+// i if it is a grapheme boundary, otherwise the end of the grapheme it is inside of
+fn snap_forward(text: &str, i: usize) -> usize {
+    if i == 0 {
+        0
+    } else {
+        next_boundary(text, prev_boundary(text, i))
+    }
+}
+
+// start of the word before i, skipping any whitespace between
+pub fn prev_word_boundary(text: &str, i: usize) -> usize {
+    text[..i].split_word_bound_indices()
+        .rev()
+        .find(|(_, word)| !word.trim().is_empty())
+        .map_or(0, |(start, _)| start)
+}
+
+// end of the word after i, skipping any whitespace between
+pub fn next_word_boundary(text: &str, i: usize) -> usize {
+    text[i..].split_word_bound_indices()
+        .find(|(_, word)| !word.trim().is_empty())
+        .map_or(text.len(), |(start, word)| i + start + word.len())
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // replaces the text, putting the caret at the end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.caret = self.text.len();
+        self.anchor = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    // the selected byte range, None when nothing is selected
+    pub fn selection(&self) -> Option<Range<usize>> {
+        match self.anchor {
+            Some(anchor) if anchor != self.caret =>
+                Some(usize::min(anchor, self.caret)..usize::max(anchor, self.caret)),
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|range| &self.text[range])
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.text.len();
+    }
+
+    // moves the caret to position, extending the selection when select is set
+    fn move_to(&mut self, position: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = position;
+    }
+
+    // without select, a selection collapses to its start instead of moving
+    pub fn left(&mut self, word: bool, select: bool) {
+        let position = match self.selection() {
+            Some(range) if !select => range.start,
+            _ if word => prev_word_boundary(&self.text, self.caret),
+            _ => prev_boundary(&self.text, self.caret),
+        };
+        self.move_to(position, select);
+    }
+
+    // without select, a selection collapses to its end instead of moving
+    pub fn right(&mut self, word: bool, select: bool) {
+        let position = match self.selection() {
+            Some(range) if !select => range.end,
+            _ if word => next_word_boundary(&self.text, self.caret),
+            _ => next_boundary(&self.text, self.caret),
+        };
+        self.move_to(position, select);
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        self.move_to(self.text.len(), select);
+    }
+
+    // removes the selection, returning whether there was one
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some(range) => {
+                let start = range.start;
+                self.text.replace_range(range, "");
+                // what followed the selection may combine with what came before it
+                self.caret = snap_forward(&self.text, start);
+                self.anchor = None;
+                true
+            },
+            None => {
+                self.anchor = None;
+                false
+            },
+        }
+    }
+
+    // replaces the selection with text, control characters like new lines are dropped
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if text.is_empty() {
+            return
+        }
+        self.text.insert_str(self.caret, &text);
+        // the inserted text may have joined with a combining character after it
+        self.caret = snap_forward(&self.text, self.caret + text.len());
+    }
+
+    // deletes the selection, or the grapheme or word before the caret
+    pub fn backspace(&mut self, word: bool) {
+        if self.delete_selection() {
+            return
+        }
+        let start = if word {
+            prev_word_boundary(&self.text, self.caret)
+        } else {
+            prev_boundary(&self.text, self.caret)
+        };
+        self.text.replace_range(start..self.caret, "");
+        self.caret = start;
+    }
+
+    // deletes the selection, or the grapheme or word after the caret
+    pub fn delete(&mut self, word: bool) {
+        if self.delete_selection() {
+            return
+        }
+        let end = if word {
+            next_word_boundary(&self.text, self.caret)
+        } else {
+            next_boundary(&self.text, self.caret)
+        };
+        self.text.replace_range(self.caret..end, "");
+    }
+
+    // removes and returns the selected text
+    pub fn cut(&mut self) -> Option<String> {
+        let selected = self.selected_text().map(str::to_string);
+        self.delete_selection();
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::Clipboard;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.set_text(text);
+        editor
+    }
+
+    #[test]
+    fn steps_over_combining_marks() {
+        // e followed by a combining acute accent is one grapheme of three bytes
+        let text = "ae\u{301}b";
+        assert_eq!(next_boundary(text, 1), 4);
+        assert_eq!(prev_boundary(text, 4), 1);
+        let mut editor = editor(text);
+        editor.left(false, false);
+        editor.left(false, false);
+        assert_eq!(editor.caret(), 1);
+        editor.delete(false);
+        assert_eq!(editor.text(), "ab");
+    }
+
+    #[test]
+    fn steps_over_emoji_zwj_sequences() {
+        // a family emoji made of four people joined by zero width joiners
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}";
+        let text = format!("x{}y", family);
+        assert_eq!(next_boundary(&text, 1), 1 + family.len());
+        assert_eq!(prev_boundary(&text, 1 + family.len()), 1);
+        let mut editor = editor(&text);
+        editor.left(false, false);
+        editor.backspace(false);
+        assert_eq!(editor.text(), "xy");
+        assert_eq!(editor.caret(), 1);
+    }
+
+    #[test]
+    fn word_jumps() {
+        let text = "spawn  basic 3";
+        assert_eq!(next_word_boundary(text, 0), 5);
+        assert_eq!(next_word_boundary(text, 5), 12);
+        assert_eq!(prev_word_boundary(text, text.len()), 13);
+        assert_eq!(prev_word_boundary(text, 12), 7);
+        assert_eq!(prev_word_boundary(text, 7), 0);
+
+        let mut editor = editor(text);
+        editor.backspace(true);
+        assert_eq!(editor.text(), "spawn  basic ");
+        editor.home(false);
+        editor.delete(true);
+        assert_eq!(editor.text(), "  basic ");
+    }
+
+    #[test]
+    fn selection_is_replaced_and_deleted() {
+        let mut editor = editor("hello world");
+        editor.left(true, false);
+        editor.right(true, true);
+        assert_eq!(editor.selected_text(), Some("world"));
+        editor.insert("there");
+        assert_eq!(editor.text(), "hello there");
+        assert_eq!(editor.selection(), None);
+
+        editor.home(false);
+        editor.right(false, true);
+        editor.right(false, true);
+        assert_eq!(editor.selection(), Some(0..2));
+        editor.backspace(false);
+        assert_eq!(editor.text(), "llo there");
+        assert_eq!(editor.caret(), 0);
+
+        editor.select_all();
+        editor.delete(false);
+        assert!(editor.is_empty());
+    }
+
+    #[test]
+    fn moving_without_select_collapses_the_selection() {
+        let mut editor = editor("abc");
+        editor.select_all();
+        editor.left(false, false);
+        assert_eq!((editor.caret(), editor.selection()), (0, None));
+        editor.select_all();
+        editor.right(false, false);
+        assert_eq!((editor.caret(), editor.selection()), (3, None));
+    }
+
+    #[test]
+    fn cut_and_paste() {
+        let mut clipboard = Clipboard::private();
+        let mut editor = editor("teleport 1 2");
+        editor.home(false);
+        editor.right(true, true);
+        clipboard.set(&editor.cut().unwrap());
+        assert_eq!(editor.text(), " 1 2");
+        assert_eq!(editor.cut(), None);
+
+        editor.end(false);
+        editor.insert(" ");
+        editor.insert(&clipboard.get());
+        assert_eq!(editor.text(), " 1 2 teleport");
+        // new lines pasted into a single line are dropped
+        editor.insert("\nx");
+        assert_eq!(editor.text(), " 1 2 teleportx");
+    }
+}
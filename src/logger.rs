@@ -0,0 +1,79 @@
+// sends log crate output to the chatbox, as well as stderr natively or the browser console on the web
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+// records are logged from anywhere, so they wait here until the game drains them into the chatbox
+static PENDING: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+
+struct ChatboxLogger {
+    #[cfg(not(target_arch = "wasm32"))]
+    inner: env_logger::Logger,
+}
+
+impl ChatboxLogger {
+    // warnings from anywhere and info from this crate, dependencies like wgpu are too noisy otherwise
+    fn shows_in_chatbox(metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+            || (metadata.level() <= Level::Info && metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+    }
+
+    fn forwards(&self, metadata: &Metadata) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                metadata.level() <= Level::Warn
+            } else {
+                self.inner.enabled(metadata)
+            }
+        }
+    }
+}
+
+impl Log for ChatboxLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        Self::shows_in_chatbox(metadata) || self.forwards(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.forwards(record.metadata()) {
+            cfg_if::cfg_if! {
+                if #[cfg(target_arch = "wasm32")] {
+                    console_log::log(record);
+                } else {
+                    self.inner.log(record);
+                }
+            }
+        }
+        if Self::shows_in_chatbox(record.metadata()) {
+            if let Ok(mut pending) = PENDING.lock() {
+                pending.push((record.level(), record.args().to_string()));
+            }
+        }
+    }
+
+    fn flush(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.inner.flush();
+    }
+}
+
+pub fn init() -> Result<(), SetLoggerError> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let logger = ChatboxLogger {};
+            let max_level = LevelFilter::Info;
+        } else {
+            let inner = env_logger::Builder::from_default_env().build();
+            let max_level = std::cmp::max(inner.filter(), LevelFilter::Info);
+            let logger = ChatboxLogger { inner };
+        }
+    }
+    log::set_boxed_logger(Box::new(logger))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+// records logged since the last call, oldest first
+pub fn drain() -> Vec<(Level, String)> {
+    PENDING.lock().map(|mut pending| std::mem::take(&mut *pending)).unwrap_or_default()
+}
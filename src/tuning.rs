@@ -0,0 +1,277 @@
+// gameplay constants that can be changed while the game runs, from the console or tuning.cfg
+use crate::{State, chatbox::MessageCategory, console::{CommandRegistry, Args, ArgType, arg, optional}};
+
+pub struct TuningVar {
+    pub name: &'static str,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+    pub description: &'static str,
+}
+
+macro_rules! tuning {
+    ($($field:ident: $name:literal = $default:expr, $min:literal..=$max:literal, $description:literal;)*) => {
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Tuning {
+            $(pub $field: f32,)*
+        }
+
+        impl Default for Tuning {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl Tuning {
+            pub const VARS: &'static [TuningVar] = &[
+                $(TuningVar { name: $name, default: $default, min: $min, max: $max, description: $description },)*
+            ];
+
+            pub fn get(&self, name: &str) -> Option<f32> {
+                match name {
+                    $($name => Some(self.$field),)*
+                    _ => None,
+                }
+            }
+
+            fn get_mut(&mut self, name: &str) -> Option<&mut f32> {
+                match name {
+                    $($name => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+tuning! {
+    player_jump_speed: "player.jump_speed" = 4.0, 0.0..=20.0, "upward speed while holding jump";
+    player_jump_hold_max: "player.jump_hold_max" = 0.3, 0.0..=2.0, "longest a held jump keeps rising";
+    player_jump_hold_min: "player.jump_hold_min" = 0.15, 0.0..=2.0, "shortest a tapped jump keeps rising";
+    player_fall_speed: "player.fall_speed" = 5.0, 0.0..=30.0, "terminal falling speed";
+    player_gravity: "player.gravity" = 22.0, 0.0..=100.0, "vertical acceleration towards the target speed";
+    player_move_speed: "player.move_speed" = 7.0, 0.0..=30.0, "top running speed";
+    player_accel_x: "player.accel_x" = 10.0, 0.0..=100.0, "horizontal acceleration in the air";
+    player_ground_accel_multiplier: "player.ground_accel_multiplier" = 2.0, 0.0..=10.0, "horizontal acceleration multiplier on the ground";
+    player_turnaround_multiplier: "player.turnaround_multiplier" = 14.0, 0.0..=50.0, "acceleration multiplier when reversing direction";
+    player_coyote_time: "player.coyote_time" = 0.1, 0.0..=1.0, "how long after leaving a ledge a jump is allowed";
+    player_jump_buffer: "player.jump_buffer" = 0.12, 0.0..=1.0, "how long a jump press is remembered before landing";
+    player_apex_threshold: "player.apex_threshold" = 1.5, 0.0..=10.0, "vertical speed below which held jumps hang";
+    player_apex_gravity: "player.apex_gravity" = 0.5, 0.0..=2.0, "gravity multiplier at the top of a held jump";
+    player_fall_gravity: "player.fall_gravity" = 1.6, 0.0..=5.0, "gravity multiplier while falling";
+    player_wall_slide_speed: "player.wall_slide_speed" = 1.5, 0.0..=30.0, "max fall speed while pushing into a wall";
+    player_wall_jump_speed: "player.wall_jump_speed" = 7.0, 0.0..=30.0, "horizontal speed of a wall jump";
+    player_wall_jump_lockout: "player.wall_jump_lockout" = 0.15, 0.0..=1.0, "how long input is ignored after a wall jump";
+    dash_speed: "dash.speed" = 20.0, 0.0..=60.0, "speed at the start of a dash";
+    dash_end_speed: "dash.end_speed" = 4.0, 0.0..=60.0, "speed a dash has slowed to when it ends";
+    dash_duration: "dash.duration" = 0.2, 0.01..=2.0, "length of a dash";
+    dash_peak: "dash.peak" = 0.1, 0.0..=2.0, "time into a dash when it starts slowing down";
+    dash_hang_time: "dash.hang_time" = 0.05, 0.0..=1.0, "pause in the air after a dash";
+    dash_cooldown: "dash.cooldown" = 0.1, 0.0..=5.0, "time before dashing again";
+    basic_move_speed: "basic.move_speed" = 5.0, 0.0..=30.0, "walking speed of basic enemies";
+    basic_accel_x: "basic.accel_x" = 22.0, 0.0..=100.0, "horizontal acceleration of basic enemies";
+    basic_gravity: "basic.gravity" = 22.0, 0.0..=100.0, "vertical acceleration of basic enemies";
+    jumping_move_speed: "jumping.move_speed" = 2.0, 0.0..=30.0, "walking speed of jumping enemies";
+    jumping_jump_interval: "jumping.jump_interval" = 2.0, 0.1..=20.0, "seconds between jumps of jumping enemies";
+    jumping_jump_speed: "jumping.jump_speed" = 4.0, 0.0..=20.0, "jump speed of jumping enemies";
+    jumping_gravity: "jumping.gravity" = 22.0, 0.0..=100.0, "vertical acceleration of jumping enemies";
+    world_spawn_time_basic: "world.spawn_time_basic" = 4.0, 0.1..=60.0, "seconds between basic enemy spawns on level 1";
+    world_spawn_time_jumping: "world.spawn_time_jumping" = 6.0, 0.1..=60.0, "seconds between jumping enemy spawns on level 1";
+    camera_zoom: "camera.zoom" = 16.0, 2.0..=100.0, "world units that fit across the shorter side of the window";
+    camera_zoom_speed: "camera.zoom_speed" = 3.0, 0.1..=50.0, "how quickly the camera eases to a new zoom";
+    camera_dead_zone_x: "camera.dead_zone_x" = 1.5, 0.0..=20.0, "how far the player moves sideways before the camera follows";
+    camera_dead_zone_y: "camera.dead_zone_y" = 1.0, 0.0..=20.0, "how far the player moves vertically before the camera follows";
+    camera_look_ahead: "camera.look_ahead" = 0.25, 0.0..=2.0, "seconds of the player's velocity the camera looks ahead by";
+    camera_follow_speed: "camera.follow_speed" = 6.0, 0.1..=50.0, "how quickly the camera catches up to where it should be";
+    camera_shake_offset: "camera.shake_offset" = 0.5, 0.0..=5.0, "furthest the strongest shake moves the camera";
+    camera_shake_decay: "camera.shake_decay" = 1.5, 0.0..=10.0, "trauma the shake loses per second";
+}
+
+impl Tuning {
+    pub const CONFIG_PATH: &'static str = "tuning.cfg";
+
+    pub fn var(name: &str) -> Option<&'static TuningVar> {
+        Tuning::VARS.iter().find(|var| var.name == name)
+    }
+
+    pub fn names() -> Vec<String> {
+        Tuning::VARS.iter().map(|var| var.name.to_string()).collect()
+    }
+
+    // rejects unknown names and values outside the variable's range
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let var = Tuning::var(name).ok_or_else(|| format!("unknown variable {}", name))?;
+        if !(var.min..=var.max).contains(&value) {
+            return Err(format!("{} must be between {} and {}", name, var.min, var.max))
+        }
+        *self.get_mut(name).expect("every var has a field") = value;
+        Ok(())
+    }
+
+    // one "set <name> <value>" line per variable, the same syntax as the console command
+    pub fn to_config_string(&self) -> String {
+        Tuning::VARS.iter()
+            .map(|var| format!("set {} {}\n", var.name, self.get(var.name).expect("every var has a field")))
+            .collect()
+    }
+
+    // starts from the defaults, so a config only needs the values it changes
+    pub fn from_config_str(config: &str) -> Result<Tuning, String> {
+        let mut tuning = Tuning::default();
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let result = match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["set", name, value] => value.parse::<f32>()
+                    .map_err(|_| format!("{} is not a number", value))
+                    .and_then(|value| tuning.set(name, value)),
+                _ => Err("expected set <name> <value>".to_string()),
+            };
+            result.map_err(|err| format!("line {}: {}", number + 1, err))?;
+        }
+        Ok(tuning)
+    }
+
+    pub fn load() -> Tuning {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Tuning::default()
+            } else {
+                match std::fs::read_to_string(Self::CONFIG_PATH) {
+                    Ok(config) => Tuning::from_config_str(&config).unwrap_or_else(|err| {
+                        log::warn!("Couldn't parse {}: {}", Self::CONFIG_PATH, err);
+                        Tuning::default()
+                    }),
+                    Err(_) => Tuning::default(),
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Err("saving tuning is not supported on web".to_string())
+            } else {
+                std::fs::write(Self::CONFIG_PATH, self.to_config_string()).map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+// polls tuning.cfg for changes so edits in a text editor apply without restarting
+pub struct TuningWatcher {
+    #[cfg(not(target_arch = "wasm32"))]
+    last_modified: Option<std::time::SystemTime>,
+    timer: f32,
+}
+
+impl Default for TuningWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TuningWatcher {
+    const POLL_INTERVAL: f32 = 0.5;
+
+    // starts from the file as it is now, which Tuning::load already read
+    pub fn new() -> Self {
+        let mut watcher = Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            last_modified: None,
+            timer: 0.0,
+        };
+        watcher.mark_saved();
+        watcher
+    }
+
+    // returns the newly loaded tuning, or an error message, when the file changed since the last poll
+    pub fn poll(&mut self, delta_time: f32) -> Option<Result<Tuning, String>> {
+        self.timer += delta_time;
+        if self.timer < Self::POLL_INTERVAL {
+            return None
+        }
+        self.timer = 0.0;
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                None
+            } else {
+                let modified = std::fs::metadata(Tuning::CONFIG_PATH).and_then(|meta| meta.modified()).ok()?;
+                if self.last_modified == Some(modified) {
+                    return None
+                }
+                self.last_modified = Some(modified);
+                Some(std::fs::read_to_string(Tuning::CONFIG_PATH)
+                    .map_err(|err| err.to_string())
+                    .and_then(|config| Tuning::from_config_str(&config)))
+            }
+        }
+    }
+
+    // remembers our own saves so they aren't reported as outside changes
+    pub fn mark_saved(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.last_modified = std::fs::metadata(Tuning::CONFIG_PATH).and_then(|meta| meta.modified()).ok();
+        }
+    }
+}
+
+fn var_names() -> Vec<String> {
+    Tuning::names()
+}
+
+// saves after a change from the console, without the watcher reloading it as an outside edit
+fn save_tuning(state: &mut State) -> Result<(), String> {
+    state.tuning.save().map_err(|err| format!("Couldn't save tuning: {}", err))?;
+    state.tuning_watcher.mark_saved();
+    Ok(())
+}
+
+pub fn register_commands(registry: &mut CommandRegistry<State>) {
+    registry.register("set",
+        vec![arg("name", ArgType::Choice(var_names)), arg("value", ArgType::Float)],
+        "changes a tuning variable and saves it to tuning.cfg",
+        |state: &mut State, args: &Args| {
+            state.tuning.set(args.word(0), args.float(1))?;
+            save_tuning(state)
+        });
+    registry.register("get",
+        vec![arg("name", ArgType::Choice(var_names))],
+        "shows a tuning variable with its default, range and description",
+        |state: &mut State, args: &Args| {
+            let var = Tuning::var(args.word(0)).ok_or_else(|| format!("unknown variable {}", args.word(0)))?;
+            let value = state.tuning.get(var.name).expect("every var has a field");
+            state.chatbox.print(MessageCategory::Command, &format!("{} = {} (default {}, {} to {})", var.name, value, var.default, var.min, var.max));
+            state.chatbox.print(MessageCategory::Command, var.description);
+            Ok(())
+        });
+    registry.register("tuning",
+        vec![optional("prefix", ArgType::Word)],
+        "lists tuning variables, optionally only those starting with prefix",
+        |state: &mut State, args: &Args| {
+            let prefix = if args.len() == 1 { args.word(0) } else { "" };
+            for var in Tuning::VARS.iter().filter(|var| var.name.starts_with(prefix)) {
+                let value = state.tuning.get(var.name).expect("every var has a field");
+                state.chatbox.print(MessageCategory::Command, &format!("{} = {}", var.name, value));
+            }
+            Ok(())
+        });
+    registry.register("tuning_reset",
+        vec![optional("name", ArgType::Choice(var_names))],
+        "puts a tuning variable, or all of them, back to the default",
+        |state: &mut State, args: &Args| {
+            if args.len() == 1 {
+                let var = Tuning::var(args.word(0)).ok_or_else(|| format!("unknown variable {}", args.word(0)))?;
+                state.tuning.set(var.name, var.default)?;
+            } else {
+                state.tuning = Tuning::default();
+            }
+            save_tuning(state)
+        });
+}
@@ -0,0 +1,480 @@
+use cgmath::{Vector2, Vector4};
+
+use crate::graphics::text::{FontMetricsInfo, FontInfoContainer, BaseFontInfoContainer};
+
+// immediate mode ui: widgets are declared every frame and produce draw commands,
+// which the render engine turns into ui_texture_renderer and font_renderer calls.
+// nothing in here touches the gpu so layout and hit testing can run anywhere.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub min: Vector2<f32>,
+    pub size: Vector2<f32>,
+}
+
+impl Rect {
+    pub fn new(min: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Self { min, size }
+    }
+
+    pub fn max(&self) -> Vector2<f32> {
+        self.min + self.size
+    }
+
+    pub fn center(&self) -> Vector2<f32> {
+        self.min + self.size / 2.0
+    }
+
+    pub fn contains(&self, point: Vector2<f32>) -> bool {
+        let max = self.max();
+        point.x >= self.min.x && point.y >= self.min.y && point.x < max.x && point.y < max.y
+    }
+
+    // smallest rect containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let min = Vector2::new(f32::min(self.min.x, other.min.x), f32::min(self.min.y, other.min.y));
+        let max = Vector2::new(f32::max(self.max().x, other.max().x), f32::max(self.max().y, other.max().y));
+        Rect::new(min, max - min)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Rect { rect: Rect, color: Vector4<f32> },
+    // position is the left end of the text's baseline, like FontRenderer expects
+    Text { text: String, position: Vector2<f32>, color: Vector4<f32>, scale: f32 },
+}
+
+// the input the ui reacts to this frame, positions are in window pixels
+#[derive(Clone, Copy, Debug)]
+pub struct UiInput {
+    pub mouse_position: Vector2<f32>,
+    pub mouse_down: bool,
+    pub mouse_pressed: bool,
+    // keyboard and gamepad navigation between focusable widgets
+    pub focus_next: bool,
+    pub focus_prev: bool,
+    pub activate: bool,
+    // -1, 0 or 1, nudges the focused slider or list
+    pub adjust: i32,
+}
+
+pub type WidgetId = u32;
+
+// what the ui remembers between frames
+#[derive(Default)]
+pub struct UiState {
+    // widget the mouse went down on, e.g. the slider being dragged
+    active: Option<WidgetId>,
+    focused: Option<WidgetId>,
+    // number of focusable widgets declared last frame, for wrapping focus navigation
+    focusable_count: u32,
+}
+
+pub struct Style {
+    pub padding: f32,
+    pub spacing: f32,
+    pub text_color: Vector4<f32>,
+    pub panel_color: Vector4<f32>,
+    pub widget_color: Vector4<f32>,
+    pub hover_color: Vector4<f32>,
+    pub active_color: Vector4<f32>,
+    pub focus_color: Vector4<f32>,
+    pub slider_width: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            padding: 8.0,
+            spacing: 6.0,
+            text_color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            panel_color: Vector4::new(0.0, 0.0, 0.0, 0.7),
+            widget_color: Vector4::new(0.25, 0.25, 0.3, 1.0),
+            hover_color: Vector4::new(0.35, 0.35, 0.45, 1.0),
+            active_color: Vector4::new(0.5, 0.45, 0.25, 1.0),
+            focus_color: Vector4::new(1.0, 0.8, 0.3, 1.0),
+            slider_width: 200.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+struct Layout {
+    direction: Direction,
+    cursor: Vector2<f32>,
+    // everything placed in this layout so far
+    bounds: Option<Rect>,
+}
+
+pub struct Ui<'a> {
+    state: &'a mut UiState,
+    input: UiInput,
+    font: &'a FontMetricsInfo,
+    pub style: Style,
+    layouts: Vec<Layout>,
+    commands: Vec<DrawCommand>,
+    next_id: WidgetId,
+}
+
+impl<'a> Ui<'a> {
+    pub fn new(state: &'a mut UiState, input: UiInput, font: &'a FontMetricsInfo, origin: Vector2<f32>) -> Self {
+        // move focus before any widget looks at it
+        if state.focusable_count > 0 {
+            let count = state.focusable_count;
+            if input.focus_next {
+                state.focused = Some(state.focused.map_or(0, |f| (f + 1) % count));
+            } else if input.focus_prev {
+                state.focused = Some(state.focused.map_or(count - 1, |f| (f + count - 1) % count));
+            }
+        }
+        if !input.mouse_down {
+            state.active = None;
+        }
+        Self {
+            state,
+            input,
+            font,
+            style: Style::default(),
+            layouts: vec![Layout { direction: Direction::Column, cursor: origin, bounds: None }],
+            commands: vec![],
+            next_id: 0,
+        }
+    }
+
+    // the draw commands in back to front order
+    pub fn finish(self) -> Vec<DrawCommand> {
+        self.state.focusable_count = self.next_id;
+        if self.state.focused.map_or(false, |f| f >= self.next_id) {
+            self.state.focused = None;
+        }
+        self.commands
+    }
+
+    pub fn row<R>(&mut self, contents: impl FnOnce(&mut Ui<'a>) -> R) -> R {
+        self.nested(Direction::Row, contents).0
+    }
+
+    pub fn column<R>(&mut self, contents: impl FnOnce(&mut Ui<'a>) -> R) -> R {
+        self.nested(Direction::Column, contents).0
+    }
+
+    // a column with a background behind it
+    pub fn panel<R>(&mut self, contents: impl FnOnce(&mut Ui<'a>) -> R) -> R {
+        let background = self.commands.len();
+        let padding = Vector2::new(self.style.padding, self.style.padding);
+        let start = self.cursor();
+        self.set_cursor(start + padding);
+        let (result, bounds) = self.nested(Direction::Column, contents);
+        let inner = bounds.unwrap_or(Rect::new(start + padding, Vector2::new(0.0, 0.0)));
+        let rect = Rect::new(start, inner.max() + padding - start);
+        self.commands.insert(background, DrawCommand::Rect { rect, color: self.style.panel_color });
+        self.set_cursor(start);
+        self.place(rect.size);
+        result
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let size = self.text_size(text);
+        let rect = self.place(size);
+        self.push_text(text, rect, self.style.text_color);
+    }
+
+    // returns true on the frame the button is clicked or activated
+    pub fn button(&mut self, text: &str) -> bool {
+        let size = self.text_size(text);
+        let rect = self.place(size);
+        let (clicked, hovered, focused) = self.interact(rect);
+        let color = if self.state.active == Some(self.last_id()) {
+            self.style.active_color
+        } else if hovered {
+            self.style.hover_color
+        } else {
+            self.style.widget_color
+        };
+        self.push_rect(rect, color);
+        if focused {
+            self.push_outline(rect);
+        }
+        self.push_text(text, rect, self.style.text_color);
+        clicked
+    }
+
+    // returns true if value changed
+    pub fn checkbox(&mut self, text: &str, value: &mut bool) -> bool {
+        let text = format!("[{}] {}", if *value { "x" } else { " " }, text);
+        let clicked = self.button(&text);
+        if clicked {
+            *value = !*value;
+        }
+        clicked
+    }
+
+    // horizontal slider that can be dragged, clicked or nudged with adjust, returns true if value changed
+    pub fn slider(&mut self, text: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let label_size = self.text_size(text);
+        let size = Vector2::new(self.style.slider_width, label_size.y);
+        let rect = self.place(size);
+        let (_, hovered, focused) = self.interact(rect);
+        let id = self.last_id();
+
+        let old = *value;
+        if self.state.active == Some(id) {
+            *value = slider_value(rect, self.input.mouse_position.x, min, max);
+        } else if focused && self.input.adjust != 0 {
+            *value = f32::clamp(*value + (max - min) * 0.05 * self.input.adjust as f32, min, max);
+        }
+
+        let fraction = if max > min { (*value - min) / (max - min) } else { 0.0 };
+        self.push_rect(rect, if hovered { self.style.hover_color } else { self.style.widget_color });
+        self.push_rect(Rect::new(rect.min, Vector2::new(rect.size.x * fraction, rect.size.y)), self.style.active_color);
+        if focused {
+            self.push_outline(rect);
+        }
+        self.push_text(&format!("{}: {:.2}", text, *value), rect, self.style.text_color);
+        *value != old
+    }
+
+    // one button per item, the selected one highlighted, returns true if the selection changed
+    pub fn list(&mut self, items: &[String], selected: &mut usize) -> bool {
+        let old = *selected;
+        self.column(|ui| {
+            for (i, item) in items.iter().enumerate() {
+                let text = if i == *selected { format!("> {}", item) } else { item.clone() };
+                if ui.button(&text) {
+                    *selected = i;
+                }
+            }
+        });
+        *selected != old
+    }
+
+    // reserves space for a custom widget and returns where it goes
+    pub fn allocate(&mut self, size: Vector2<f32>) -> Rect {
+        self.place(size)
+    }
+
+    fn nested<R>(&mut self, direction: Direction, contents: impl FnOnce(&mut Ui<'a>) -> R) -> (R, Option<Rect>) {
+        let cursor = self.cursor();
+        self.layouts.push(Layout { direction, cursor, bounds: None });
+        let result = contents(self);
+        let layout = self.layouts.pop().expect("layout stack is never empty");
+        if let Some(bounds) = layout.bounds {
+            self.set_cursor(bounds.min);
+            self.place(bounds.size);
+        }
+        (result, layout.bounds)
+    }
+
+    fn cursor(&self) -> Vector2<f32> {
+        self.layouts.last().expect("layout stack is never empty").cursor
+    }
+
+    fn set_cursor(&mut self, cursor: Vector2<f32>) {
+        self.layouts.last_mut().expect("layout stack is never empty").cursor = cursor;
+    }
+
+    // places a widget of size at the cursor and advances the cursor past it
+    fn place(&mut self, size: Vector2<f32>) -> Rect {
+        let spacing = self.style.spacing;
+        let layout = self.layouts.last_mut().expect("layout stack is never empty");
+        let rect = Rect::new(layout.cursor, size);
+        layout.cursor = match layout.direction {
+            Direction::Row => Vector2::new(rect.max().x + spacing, rect.min.y),
+            Direction::Column => Vector2::new(rect.min.x, rect.max().y + spacing),
+        };
+        layout.bounds = Some(layout.bounds.map_or(rect, |bounds| bounds.union(&rect)));
+        rect
+    }
+
+    // handles mouse and focus for a focusable widget, returns (clicked, hovered, focused)
+    fn interact(&mut self, rect: Rect) -> (bool, bool, bool) {
+        // every interactive widget is focusable, so ids double as focus order
+        let id = self.next_id;
+        self.next_id += 1;
+        let focus_index = id;
+
+        let hovered = rect.contains(self.input.mouse_position);
+        let mut clicked = false;
+        if hovered && self.input.mouse_pressed {
+            self.state.active = Some(id);
+            self.state.focused = Some(focus_index);
+            clicked = true;
+        }
+        let focused = self.state.focused == Some(focus_index);
+        if focused && self.input.activate {
+            clicked = true;
+        }
+        (clicked, hovered, focused)
+    }
+
+    fn last_id(&self) -> WidgetId {
+        self.next_id - 1
+    }
+
+    fn text_size(&self, text: &str) -> Vector2<f32> {
+        let padding = self.style.padding;
+        Vector2::new(self.font.text_width(text) + padding * 2.0, self.font.line_height() + padding * 2.0)
+    }
+
+    fn push_rect(&mut self, rect: Rect, color: Vector4<f32>) {
+        self.commands.push(DrawCommand::Rect { rect, color });
+    }
+
+    fn push_outline(&mut self, rect: Rect) {
+        let color = self.style.focus_color;
+        let t = 2.0;
+        self.push_rect(Rect::new(rect.min, Vector2::new(rect.size.x, t)), color);
+        self.push_rect(Rect::new(Vector2::new(rect.min.x, rect.max().y - t), Vector2::new(rect.size.x, t)), color);
+        self.push_rect(Rect::new(rect.min, Vector2::new(t, rect.size.y)), color);
+        self.push_rect(Rect::new(Vector2::new(rect.max().x - t, rect.min.y), Vector2::new(t, rect.size.y)), color);
+    }
+
+    // text is drawn from its baseline, roughly 80% of a line down from the top of the padded area
+    fn push_text(&mut self, text: &str, rect: Rect, color: Vector4<f32>) {
+        let position = Vector2::new(
+            rect.min.x + self.style.padding,
+            rect.min.y + self.style.padding + self.font.line_height() * 0.8);
+        self.commands.push(DrawCommand::Text { text: text.to_string(), position, color, scale: 1.0 });
+    }
+}
+
+// value of a slider spanning rect when the mouse is at mouse_x
+pub fn slider_value(rect: Rect, mouse_x: f32, min: f32, max: f32) -> f32 {
+    let fraction = f32::clamp((mouse_x - rect.min.x) / f32::max(1.0, rect.size.x), 0.0, 1.0);
+    min + (max - min) * fraction
+}
+
+// splits commands into runs of rects and runs of same sized text, so each run can be drawn with one renderer call
+pub fn batches(commands: &[DrawCommand]) -> Vec<&[DrawCommand]> {
+    let same_batch = |a: &DrawCommand, b: &DrawCommand| match (a, b) {
+        (DrawCommand::Rect { .. }, DrawCommand::Rect { .. }) => true,
+        (DrawCommand::Text { scale: a, .. }, DrawCommand::Text { scale: b, .. }) => a == b,
+        _ => false,
+    };
+    let mut batches = vec![];
+    let mut start = 0;
+    for i in 1..=commands.len() {
+        if i == commands.len() || !same_batch(&commands[i], &commands[start]) {
+            batches.push(&commands[start..i]);
+            start = i;
+        }
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // no glyphs, so every piece of text is zero wide and widgets are just their padding
+    fn font() -> FontMetricsInfo {
+        FontMetricsInfo { char_data: HashMap::new(), font_size: 20.0, height: 20.0 }
+    }
+
+    fn input(mouse_position: Vector2<f32>, mouse_down: bool, mouse_pressed: bool) -> UiInput {
+        UiInput { mouse_position, mouse_down, mouse_pressed, focus_next: false, focus_prev: false, activate: false, adjust: 0 }
+    }
+
+    fn idle() -> UiInput {
+        input(Vector2::new(-100.0, -100.0), false, false)
+    }
+
+    #[test]
+    fn column_stacks_down_and_row_stacks_right() {
+        let (mut state, font) = (UiState::default(), font());
+        let mut ui = Ui::new(&mut state, idle(), &font, Vector2::new(10.0, 20.0));
+        let spacing = ui.style.spacing;
+        let first = ui.allocate(Vector2::new(50.0, 30.0));
+        let second = ui.allocate(Vector2::new(40.0, 10.0));
+        assert_eq!(first, Rect::new(Vector2::new(10.0, 20.0), Vector2::new(50.0, 30.0)));
+        assert_eq!(second.min, Vector2::new(10.0, 50.0 + spacing));
+
+        let (a, b) = ui.row(|ui| (ui.allocate(Vector2::new(10.0, 15.0)), ui.allocate(Vector2::new(20.0, 5.0))));
+        assert_eq!(a.min, Vector2::new(10.0, second.max().y + spacing));
+        assert_eq!(b.min, Vector2::new(a.max().x + spacing, a.min.y));
+        // the column continues below the tallest thing in the row
+        let after = ui.allocate(Vector2::new(5.0, 5.0));
+        assert_eq!(after.min, Vector2::new(10.0, a.max().y + spacing));
+    }
+
+    #[test]
+    fn panel_pads_its_contents_and_draws_behind_them() {
+        let (mut state, font) = (UiState::default(), font());
+        let mut ui = Ui::new(&mut state, idle(), &font, Vector2::new(0.0, 0.0));
+        let padding = ui.style.padding;
+        let inner = ui.panel(|ui| ui.allocate(Vector2::new(30.0, 40.0)));
+        assert_eq!(inner.min, Vector2::new(padding, padding));
+        let commands = ui.finish();
+        assert_eq!(commands[0], DrawCommand::Rect {
+            rect: Rect::new(Vector2::new(0.0, 0.0), Vector2::new(30.0 + padding * 2.0, 40.0 + padding * 2.0)),
+            color: Style::default().panel_color,
+        });
+    }
+
+    // the color of the first rect drawn, which is the button's background
+    fn button_color(state: &mut UiState, font: &FontMetricsInfo, input: UiInput) -> (bool, Vector4<f32>) {
+        let mut ui = Ui::new(state, input, font, Vector2::new(0.0, 0.0));
+        let clicked = ui.button("ok");
+        match ui.finish().first() {
+            Some(DrawCommand::Rect { color, .. }) => (clicked, *color),
+            other => panic!("expected the button's rect, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn button_hot_active_and_click() {
+        let (mut state, font) = (UiState::default(), font());
+        let style = Style::default();
+        let inside = Vector2::new(5.0, 5.0);
+
+        assert_eq!(button_color(&mut state, &font, idle()), (false, style.widget_color));
+        assert_eq!(button_color(&mut state, &font, input(inside, false, false)), (false, style.hover_color));
+        // clicks on the frame the mouse goes down and stays active while held
+        assert_eq!(button_color(&mut state, &font, input(inside, true, true)), (true, style.active_color));
+        assert_eq!(button_color(&mut state, &font, input(inside, true, false)), (false, style.active_color));
+        assert_eq!(button_color(&mut state, &font, input(inside, false, false)), (false, style.hover_color));
+        // pressing outside doesn't click
+        assert_eq!(button_color(&mut state, &font, input(Vector2::new(500.0, 5.0), true, true)), (false, style.widget_color));
+    }
+
+    #[test]
+    fn focused_button_activates() {
+        let (mut state, font) = (UiState::default(), font());
+        // the first frame counts the focusable widgets, the second moves focus onto one
+        button_color(&mut state, &font, idle());
+        let mut focus = idle();
+        focus.focus_next = true;
+        button_color(&mut state, &font, focus);
+        let mut activate = idle();
+        activate.activate = true;
+        assert!(button_color(&mut state, &font, activate).0);
+    }
+
+    #[test]
+    fn slider_value_clamps() {
+        let rect = Rect::new(Vector2::new(100.0, 0.0), Vector2::new(200.0, 20.0));
+        assert_eq!(slider_value(rect, 50.0, 1.0, 3.0), 1.0);
+        assert_eq!(slider_value(rect, 200.0, 1.0, 3.0), 2.0);
+        assert_eq!(slider_value(rect, 1000.0, 1.0, 3.0), 3.0);
+    }
+
+    #[test]
+    fn batches_split_rect_and_text_runs_in_order() {
+        let rect = || DrawCommand::Rect { rect: Rect::new(Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0)), color: Vector4::new(1.0, 1.0, 1.0, 1.0) };
+        let text = |scale| DrawCommand::Text { text: "a".to_string(), position: Vector2::new(0.0, 0.0), color: Vector4::new(1.0, 1.0, 1.0, 1.0), scale };
+        let commands = vec![rect(), rect(), text(1.0), text(1.0), text(2.0), rect(), text(2.0)];
+        let lengths: Vec<usize> = batches(&commands).iter().map(|batch| batch.len()).collect();
+        assert_eq!(lengths, vec![2, 2, 1, 1, 1]);
+        let flattened: Vec<&DrawCommand> = batches(&commands).into_iter().flatten().collect();
+        assert!(flattened.into_iter().eq(commands.iter()));
+        assert!(batches(&[]).is_empty());
+    }
+}
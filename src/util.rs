@@ -39,6 +39,12 @@ impl<T, P> PartialOrdMinMax<P> for T
 }
 
 // scuffed
+// minutes and seconds, e.g. 1:05
+pub fn format_time(seconds: f32) -> String {
+    let seconds = f32::max(0.0, seconds) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
 pub fn is_goomba_stomping(stomper: &BoundingBox, stompee: &BoundingBox) -> bool {
     if stompee.center.y - stomper.center.y > (stomper.height / 2.0 + stompee.height / 2.0) * 0.8 {
         true
@@ -46,3 +52,15 @@ pub fn is_goomba_stomping(stomper: &BoundingBox, stompee: &BoundingBox) -> bool
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_is_minutes_and_seconds() {
+        assert_eq!(format_time(0.0), "0:00");
+        assert_eq!(format_time(61.9), "1:01");
+        assert_eq!(format_time(-3.0), "0:00");
+    }
+}
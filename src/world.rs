@@ -1,10 +1,12 @@
 use cgmath::{Vector2, Vector4, InnerSpace};
-use winit::event::MouseButton;
-use crate::{bounding_box::BoundingBox, graphics::ResolveInstance, chatbox::Chatbox};
+use crate::{input::Action, State, tuning::Tuning, console::{CommandRegistry, Args, ArgType, arg, optional}};
+use crate::{bounding_box::BoundingBox, graphics::ResolveInstance, chatbox::{Chatbox, MessageCategory}};
 use uuid::Uuid;
 use std::collections::HashMap;
-use player::Player;
-use self::{physics::{PhysicsObject, Physics}, stage::{Stage, TileType}, basic_enemy::BasicEnemy, jumping_enemy::JumpingEnemy, projectile::{Projectile, ProjectileType}};
+use player::{Player, AerialState};
+use score::{ScoreKeeper, KillType};
+use spell::SpellDef;
+use self::{physics::{PhysicsObject, Physics}, stage::{Stage, StageLayout, Decoration}, basic_enemy::BasicEnemy, jumping_enemy::JumpingEnemy, projectile::{Projectile, ProjectileType, ProjectileEffect}};
 
 pub mod jumping_enemy;
 pub mod basic_enemy;
@@ -12,6 +14,8 @@ pub mod player;
 pub mod physics;
 pub mod stage;
 pub mod projectile;
+pub mod spell;
+pub mod score;
 
 pub trait IDObject {
     fn get_uuid(&self) -> Uuid;
@@ -29,21 +33,31 @@ pub struct World {
     pub jumping_enemies: Vec<JumpingEnemy>,
     pub stage: HashMap<Uuid, Stage>,
     pub projectiles: Vec<Projectile>,
+    pub level: u32,
+    // carries over between levels, kills included
+    pub score: ScoreKeeper,
+    // score.kills when this level started
+    level_start_kills: u32,
+    // seconds spent playing this level
+    pub elapsed: f32,
+    // screen shake built up by hits since the camera last took it
+    pub trauma: f32,
 
     pub debug_objects: Vec<crate::graphics::ResolveInstance>,
 }
 
-pub enum GameStateChange {
-    PlayerLose
-}
+pub use crate::game_state::GameStateChange;
 
 impl World {
-    const SPAWN_TIME1: f32 = 4.0;
-    const SPAWN_TIME2: f32 = 6.0;
+    pub fn new(layout: &StageLayout, spells: &[SpellDef]) -> Self {
+        Self::with_level(1, layout, spells)
+    }
 
-    pub fn new() -> Self {
+    // later levels spawn enemies faster and need more kills to complete
+    pub fn with_level(level: u32, layout: &StageLayout, spells: &[SpellDef]) -> Self {
         let player = Player::new(
-            Vector2::new(-2.0, 2.0)
+            Vector2::new(-2.0, 2.0),
+            spells.to_vec(),
         );
         let basic_enemy = BasicEnemy::new(
             Vector2::new(0.0, -1.0)
@@ -55,168 +69,17 @@ impl World {
         let mut stage = HashMap::new();
         stage.insert(Uuid::new_v4(), Stage::new());
         stage.values_mut().for_each(|stage| {
-            stage.set_tile(&Vector2::new(-14, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-13, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-12, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-11, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-10, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-9, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-8, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-7, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-6, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-5, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-4, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-3, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-2, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-1, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(1, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(2, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(3, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(4, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(5, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(6, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(7, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(8, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(9, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(10, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(11, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(12, 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 9), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-14, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-13, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-12, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-11, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-10, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-9,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-8,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-7,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-6,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-5,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-4,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-3,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-2,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-1,- 9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(1, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(2, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(3, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(4, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(5, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(6, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(7, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(8, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(9, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(10, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(11, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(12, -9), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -9), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-10,8), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(13, -8), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -5), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, -1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 0), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13,3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 5), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 8), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(13, 9), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-14, -8), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -5), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, -1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 0), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14,3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 5), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 8), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-14, 9), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(0, 8), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 5), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(0, 0), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-1, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-2, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-3, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-4, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-5, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-6, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-7, 6), Some(TileType::Dirt));
-
-            // stage.set_tile(&Vector2::new(-5, 4), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-4, 5), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-14, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-13, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-12, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-11, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-10, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-9, 3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-8, 3), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-1, 0), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-1, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-1, 2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(1, 0), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(1, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(1, 2), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(-2, 1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(-3, 1), Some(TileType::Dirt));
-            
-            stage.set_tile(&Vector2::new(2, -1), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(3, -2), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(4, -3), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(5, -4), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(8, -4), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(6, -2), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(12, -4), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(10, -2), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(8, -0), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(12, -0), Some(TileType::Dirt));
-
-            stage.set_tile(&Vector2::new(10, 6), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(9, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(8, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(7, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(6, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(5, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(4, 7), Some(TileType::Dirt));
-            stage.set_tile(&Vector2::new(3, 6), Some(TileType::Dirt));
+            stage.set_layout(layout);
+
+            // stained glass windows behind the upper platforms
+            for x in [-6.0, 6.0] {
+                stage.decorations.push(Decoration {
+                    position: Vector2::new(x, -6.0),
+                    size: Vector2::new(2.0, 3.0),
+                    sprite: "tile_stained_glass.png",
+                    foreground: false,
+                });
+            }
         });
         Self {
             time_towards_next_spawn1: 0.0, 
@@ -227,14 +90,65 @@ impl World {
             stage,
             debug_objects: vec![],
             projectiles: vec![],
+            level,
+            score: ScoreKeeper::new(),
+            level_start_kills: 0,
+            elapsed: 0.0,
+            trauma: 0.0,
+        }
+    }
+
+    // how much each kind of hit shakes the screen, a trauma of 1 is the strongest shake
+    pub const STOMP_TRAUMA: f32 = 0.3;
+    pub const EXPLOSION_TRAUMA: f32 = 0.5;
+    pub const DEATH_TRAUMA: f32 = 0.6;
+
+    fn shake(&mut self, trauma: f32) {
+        self.trauma = f32::min(1.0, self.trauma + trauma);
+    }
+
+    // the box around every stage, which the camera keeps its view inside
+    pub fn bounds(&self) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        self.stage.values().filter_map(Stage::bounds).reduce(|(lo, hi), (min, max)| (
+            Vector2::new(f32::min(lo.x, min.x), f32::min(lo.y, min.y)),
+            Vector2::new(f32::max(hi.x, max.x), f32::max(hi.y, max.y)),
+        ))
+    }
+
+    // swaps in a changed level file without restarting
+    pub fn set_layout(&mut self, layout: &StageLayout) {
+        for stage in self.stage.values_mut() {
+            stage.set_layout(layout);
         }
     }
 
+    // starts the level with the score of the one before it
+    pub fn carry_over(&mut self, mut score: ScoreKeeper) {
+        score.land();
+        self.level_start_kills = score.kills;
+        self.score = score;
+    }
+
+    pub fn level_kills(&self) -> u32 {
+        self.score.kills - self.level_start_kills
+    }
+
+    pub fn kills_to_complete(&self) -> u32 {
+        2 + self.level * 3
+    }
+
+    fn spawn_time_multiplier(&self) -> f32 {
+        f32::powi(0.85, self.level as i32 - 1)
+    }
+
     // don't we need a thing to tell it how much to change?
-    pub fn update(&mut self, delta_time: f32, input_state: &crate::InputState) {
+    pub fn update(&mut self, delta_time: f32, input_state: &crate::InputState, tuning: &Tuning) -> Option<GameStateChange> {
+        self.elapsed += delta_time;
+        let alive = self.player.alive;
+
         // increment time towards next spawn, spawn if appropriate
         self.time_towards_next_spawn1 += delta_time;
-        if self.time_towards_next_spawn1 >= World::SPAWN_TIME1 {
+        if self.time_towards_next_spawn1 >= tuning.world_spawn_time_basic * self.spawn_time_multiplier() {
             let basic_enemy = BasicEnemy::new(
                 Vector2::new(0.0, -1.0)
             );
@@ -244,7 +158,7 @@ impl World {
         }
 
         self.time_towards_next_spawn2 += delta_time;
-        if self.time_towards_next_spawn2 >= World::SPAWN_TIME2 {
+        if self.time_towards_next_spawn2 >= tuning.world_spawn_time_jumping * self.spawn_time_multiplier() {
             let jumping_enemy = JumpingEnemy::new(
                 Vector2::new(7.0, 6.0)
             );
@@ -255,56 +169,61 @@ impl World {
 
 
         // fire projectiles
-        if self.player.alive && input_state.mouse_pos_edge.contains(&MouseButton::Left) {
-            let mouse_pos = input_state.mouse_position;
-            let dir = mouse_pos - self.player.physics.bounding_box.center;
-            if dir.magnitude2() != 0.0 {
-                let vel = dir.normalize() * 10.0;
-                let proj = Projectile::new(self.player.physics.bounding_box.center,
-                        // originally Basic projectile
-                        vel, projectile::ProjectileType::all()[self.player.current_projectile],
-                        physics::PhysObjType::Enemy);
-                println!("{:?}", self.player.current_projectile);
-                self.projectiles.push(proj);
-            }
+        if self.player.alive && input_state.actions.is_down(Action::Fire) {
+            // aim with the right stick if it was used more recently than the mouse
+            let target = match input_state.aim_stick {
+                Some(dir) => self.player.physics.bounding_box.center + dir,
+                None => input_state.mouse_position,
+            };
+            let projectiles = self.player.shoot(target);
+            self.projectiles.extend(projectiles);
         }
 
         // update projectiles
         let mut to_destroy = vec![];
         for i in 0..self.projectiles.len() {
+            let target = self.nearest_enemy(self.projectiles[i].position(), self.projectiles[i].def.homing_range);
             let proj = &mut self.projectiles[i];
-            proj.update(delta_time);
+            proj.update(delta_time, target);
             if !proj.alive {
                 to_destroy.push(i);
             }
         }
         for i in to_destroy.into_iter().rev() {
-            self.projectiles.remove(i);
+            let proj = self.projectiles.remove(i);
+            if proj.def.explosion_radius > 0.0 {
+                self.explode(proj.position(), proj.def.explosion_radius, proj.def.effect);
+            }
         }
 
         // update enemies
+        let mut state_change = None;
         let mut to_destroy = vec![];
         for i in 0..self.basic_enemies.len() {
             let obj = &mut self.basic_enemies[i];
-            obj.update(delta_time, &mut self.player);
+            state_change = state_change.or(obj.update(delta_time, &mut self.player, tuning));
             if !obj.alive {
                 to_destroy.push(i);
             }
         }
         for i in to_destroy.into_iter().rev() {
             self.basic_enemies.remove(i);
+            self.score.stomp(KillType::Basic);
+            self.shake(World::STOMP_TRAUMA);
         }
 
         let mut to_destroy = vec![];
         for i in 0..self.jumping_enemies.len() {
             let obj = &mut self.jumping_enemies[i];
-            obj.update(delta_time, &mut self.player);
+            state_change = state_change.or(obj.update(delta_time, &mut self.player, tuning));
             if !obj.alive {
                 to_destroy.push(i);
             }
         }
         for i in to_destroy.into_iter().rev() {
             self.jumping_enemies.remove(i);
+            self.score.stomp(KillType::Jumping);
+            self.shake(World::STOMP_TRAUMA);
         }
 
         // let move_vec = {
@@ -317,10 +236,73 @@ impl World {
         // // move player by move vec
         // self.player.physics.velocity = move_vec;
         if self.player.alive {
-            self.player.update(delta_time, input_state);
+            self.player.update(delta_time, input_state, tuning);
         }
 
         self.physics(delta_time);
+        self.apply_projectile_hits();
+
+        // animations follow the state everything ended the step in
+        let clip = self.player.clip();
+        self.player.animator.update(delta_time, clip);
+        for enemy in &mut self.basic_enemies {
+            let clip = enemy.clip();
+            enemy.animator.update(delta_time, clip);
+        }
+        for enemy in &mut self.jumping_enemies {
+            let clip = enemy.clip();
+            enemy.animator.update(delta_time, clip);
+        }
+
+        if alive && !self.player.alive {
+            self.shake(World::DEATH_TRAUMA);
+        }
+
+        // touching the ground ends a stomp combo
+        if self.player.aerial_state == AerialState::OnGround {
+            self.score.land();
+        }
+
+        if state_change.is_none() && self.level_kills() >= self.kills_to_complete() {
+            state_change = Some(GameStateChange::LevelComplete);
+        }
+        state_change
+    }
+
+    // position of the closest enemy within range of position
+    fn nearest_enemy(&self, position: Vector2<f32>, range: f32) -> Option<Vector2<f32>> {
+        self.basic_enemies.iter().map(|enemy| enemy.physics.bounding_box.center)
+            .chain(self.jumping_enemies.iter().map(|enemy| enemy.physics.bounding_box.center))
+            .map(|center| (center, (center - position).magnitude2()))
+            .filter(|(_, dist2)| *dist2 <= range * range)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(center, _)| center)
+    }
+
+    // applies effect to every enemy whose center is within radius of position
+    fn explode(&mut self, position: Vector2<f32>, radius: f32, effect: ProjectileEffect) {
+        self.shake(World::EXPLOSION_TRAUMA);
+        let in_range = |center: Vector2<f32>| (center - position).magnitude2() <= radius * radius;
+        self.basic_enemies.iter_mut()
+            .filter(|enemy| in_range(enemy.physics.bounding_box.center))
+            .for_each(|enemy| enemy.hit(effect));
+        self.jumping_enemies.iter_mut()
+            .filter(|enemy| in_range(enemy.physics.bounding_box.center))
+            .for_each(|enemy| enemy.hit(effect));
+    }
+
+    // applies projectile effects to the enemies they touched during physics
+    fn apply_projectile_hits(&mut self) {
+        for proj in self.projectiles.iter_mut() {
+            for id in proj.new_hits.drain(..) {
+                self.basic_enemies.iter_mut()
+                    .filter(|enemy| enemy.get_uuid() == id)
+                    .for_each(|enemy| enemy.hit(proj.def.effect));
+                self.jumping_enemies.iter_mut()
+                    .filter(|enemy| enemy.get_uuid() == id)
+                    .for_each(|enemy| enemy.hit(proj.def.effect));
+            }
+        }
     }
 
     fn physics(&mut self, delta_time: f32) {
@@ -381,3 +363,44 @@ impl World {
     }
 }
 
+fn enemy_names() -> Vec<String> {
+    vec!["basic".to_string(), "jumping".to_string()]
+}
+
+pub fn register_commands(registry: &mut CommandRegistry<State>) {
+    registry.register("spawn",
+        vec![arg("enemy", ArgType::Choice(enemy_names)), optional("x", ArgType::Float), optional("y", ArgType::Float)],
+        "spawns an enemy at a position, or at the mouse",
+        |state: &mut State, args: &Args| {
+            let position = if args.len() == 3 {
+                Vector2::new(args.float(1), args.float(2))
+            } else if args.len() == 1 {
+                state.input_state.mouse_position
+            } else {
+                return Err("give both x and y, or neither".to_string())
+            };
+            match args.word(0) {
+                "basic" => state.world.basic_enemies.push(BasicEnemy::new(position)),
+                _ => state.world.jumping_enemies.push(JumpingEnemy::new(position)),
+            }
+            Ok(())
+        });
+    registry.register("teleport",
+        vec![arg("x", ArgType::Float), arg("y", ArgType::Float)],
+        "moves the player to a position",
+        |state: &mut State, args: &Args| {
+            state.world.player.physics.bounding_box.center = Vector2::new(args.float(0), args.float(1));
+            state.world.player.physics.velocity = Vector2::new(0.0, 0.0);
+            Ok(())
+        });
+    registry.register("god",
+        vec![optional("enabled", ArgType::Bool)],
+        "makes the player ignore damage, toggles without an argument",
+        |state: &mut State, args: &Args| {
+            let player = &mut state.world.player;
+            player.god_mode = if args.len() == 1 { args.bool(0) } else { !player.god_mode };
+            let message = format!("god mode {}", if player.god_mode { "on" } else { "off" });
+            state.chatbox.print(MessageCategory::Command, &message);
+            Ok(())
+        });
+}
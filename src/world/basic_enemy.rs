@@ -3,8 +3,8 @@ use std::collections::HashSet;
 use cgmath::{Vector2, Vector4};
 use uuid::Uuid;
 use winit::event::VirtualKeyCode;
-use crate::{bounding_box::BoundingBox, InputState, util::is_goomba_stomping};
-use super::{GameObject, IDObject, Physics, physics::{PhysicsObject, PhysObjType}, projectile::{Projectile, ProjectileType}, World, player::Player, GameStateChange};
+use crate::{bounding_box::BoundingBox, InputState, util::is_goomba_stomping, tuning::Tuning, animation::Animator};
+use super::{GameObject, IDObject, Physics, physics::{PhysicsObject, PhysObjType}, projectile::{Projectile, ProjectileType, ProjectileEffect}, World, player::Player, GameStateChange};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum AerialState {
@@ -63,6 +63,7 @@ pub struct BasicEnemy {
     pub curse_state: CurseState, 
     resolved_on: Vec<Uuid>,
     pub alive: bool,
+    pub animator: Animator,
 }
 
 impl BasicEnemy {
@@ -71,10 +72,7 @@ impl BasicEnemy {
     const JUMP_HOLD_TIMER_MIN: f32 = 0.15;
 
     const FALL_SPEED: f32 = 5.0;
-    const ACCEL_Y: f32 = 22.0;
 
-    const ACCEL_X: f32 = 22.0;
-    const MOVE_SPEED_X: f32 = 5.0;
     const VELOCITY_ON_GROUND_MULTIPLIER_X: f32 = 2.0;
     const ACCEL_ON_GROUND_MULTIPLIER_X: f32 = 2.0;
 
@@ -96,10 +94,18 @@ impl BasicEnemy {
             curse_state: CurseState::new(),
             resolved_on: vec![],
             alive: true,
+            animator: Animator::new("basic.run"),
         }
     }
 
-    pub fn update(&mut self, delta_time: f32, player: &mut Player) -> Option<GameStateChange> {
+    pub fn clip(&self) -> &'static str {
+        match self.aerial_state {
+            AerialState::OnGround => "basic.run",
+            _ => "basic.fall",
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32, player: &mut Player, tuning: &Tuning) -> Option<GameStateChange> {
         let jumping = false;
         let hold_jump = false;
 
@@ -135,7 +141,7 @@ impl BasicEnemy {
         let accel_y = if self.aerial_state == AerialState::Jumping(0.0) {
             f32::INFINITY // this means velocity override
         } else {
-            Self::ACCEL_Y
+            tuning.basic_gravity
         } * delta_time;
 
         // move player to match target velocity y
@@ -151,7 +157,7 @@ impl BasicEnemy {
                 Self::VELOCITY_ON_GROUND_MULTIPLIER_X,
             AerialState::Falling | AerialState::Jumping(_) =>
                 1.0,
-        } * self.direction.to_f32() * Self::MOVE_SPEED_X * self.curse_state.slowdown;
+        } * self.direction.to_f32() * tuning.basic_move_speed * self.curse_state.slowdown;
 
         // find acceleration in x
         let accel_x = match self.aerial_state {
@@ -159,7 +165,7 @@ impl BasicEnemy {
                 Self::ACCEL_ON_GROUND_MULTIPLIER_X,
             AerialState::Falling | AerialState::Jumping(_) =>
                 1.0,
-        } * Self::ACCEL_X * delta_time;
+        } * tuning.basic_accel_x * delta_time;
 
         // move player to match target velocity x
         if f32::abs(self.physics.velocity.x - target_vel_x) < accel_x {
@@ -178,16 +184,23 @@ impl BasicEnemy {
                 if is_goomba_stomping(&player.physics.bounding_box, &self.physics.bounding_box) {
                     self.alive = false;
                     player.physics.velocity.y = -6.0;
+                    player.refresh_dash();
                     // insert any other blessings/curses here
-                } else {
-                    println!("player lose");
-                    player.alive = false;
+                } else if player.damage() {
                     return Some(GameStateChange::PlayerLose)
                 }
             }
             None
         });
     }
+
+    // applies the effect of a projectile that hit this enemy
+    pub fn hit(&mut self, effect: ProjectileEffect) {
+        match effect {
+            ProjectileEffect::Knockback => self.physics.velocity.y = -10.0,
+            ProjectileEffect::Slow => self.curse_state.slowdown *= 0.75,
+        }
+    }
 }
 
 impl GameObject for BasicEnemy {
@@ -228,16 +241,6 @@ impl Physics for BasicEnemy {
             self.direction = self.direction.reverse();
         }
 
-        let projectile_interaction = types.iter().find(|(t, _)| match t {
-            PhysObjType::Projectile(ProjectileType::Basic) => true,
-            PhysObjType::Projectile(ProjectileType::Slowing) => true,
-            _ => false
-        });
-        match projectile_interaction {
-            Some((PhysObjType::Projectile(ProjectileType::Basic), _)) => self.physics.velocity.y = -10.0,
-            Some((PhysObjType::Projectile(ProjectileType::Slowing), _)) => self.curse_state.slowdown *= 0.75,
-            _ => ()
-        }
         self.resolved_on.extend(types.into_iter().map(|(_, id)| id));
         delta + resolve
     }
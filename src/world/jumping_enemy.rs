@@ -3,8 +3,8 @@ use std::collections::HashSet;
 use cgmath::{Vector2, Vector4};
 use uuid::Uuid;
 use winit::event::VirtualKeyCode;
-use crate::{bounding_box::BoundingBox, InputState, util::is_goomba_stomping};
-use super::{GameObject, IDObject, Physics, physics::{PhysicsObject, PhysObjType}, projectile::{Projectile, ProjectileType}, World, player::Player, GameStateChange};
+use crate::{bounding_box::BoundingBox, InputState, util::is_goomba_stomping, tuning::Tuning, animation::Animator};
+use super::{GameObject, IDObject, Physics, physics::{PhysicsObject, PhysObjType}, projectile::{Projectile, ProjectileType, ProjectileEffect}, World, player::Player, GameStateChange};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum AerialState {
@@ -63,22 +63,18 @@ pub struct JumpingEnemy {
     pub curse_state: CurseState, 
     resolved_on: Vec<Uuid>,
     pub alive: bool,
+    pub animator: Animator,
     pub time_to_next_jump: f32,
 }
 
 impl JumpingEnemy {
-    const JUMP_TIME_INTERVAL: f32 = 2.0;
-
-    const JUMP_SPEED: f32 = 4.0;
     const JUMP_HOLD_TIMER_MAX: f32 = 0.3;
     // adjust this to get enemy jump height consistent
     const JUMP_HOLD_TIMER_MIN: f32 = 0.3;
 
     const FALL_SPEED: f32 = 5.0;
-    const ACCEL_Y: f32 = 22.0;
 
     const ACCEL_X: f32 = 2.0;
-    const MOVE_SPEED_X: f32 = 2.0;
     const VELOCITY_ON_GROUND_MULTIPLIER_X: f32 = 2.0;
     const ACCEL_ON_GROUND_MULTIPLIER_X: f32 = 2.0;
 
@@ -100,14 +96,23 @@ impl JumpingEnemy {
             curse_state: CurseState::new(),
             resolved_on: vec![],
             alive: true,
+            animator: Animator::new("jumping.idle"),
             time_to_next_jump: 0.0,
         }
     }
 
-    pub fn update(&mut self, delta_time: f32, player: &mut Player) -> Option<GameStateChange> {
+    pub fn clip(&self) -> &'static str {
+        match self.aerial_state {
+            AerialState::Jumping(_) => "jumping.jump",
+            AerialState::OnGround => "jumping.idle",
+            _ => "jumping.fall",
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32, player: &mut Player, tuning: &Tuning) -> Option<GameStateChange> {
         // update time to jump, jump if appropriate\
         self.time_to_next_jump += delta_time;
-        let jumping = self.time_to_next_jump >= JumpingEnemy::JUMP_TIME_INTERVAL;
+        let jumping = self.time_to_next_jump >= tuning.jumping_jump_interval;
         if jumping {
             self.time_to_next_jump = 0.0;
         }
@@ -138,7 +143,7 @@ impl JumpingEnemy {
 
         // find target y velocity
         let target_vel_y = match self.aerial_state {
-            AerialState::Jumping(_) => -Self::JUMP_SPEED * self.curse_state.can_jump,
+            AerialState::Jumping(_) => -tuning.jumping_jump_speed * self.curse_state.can_jump,
             _ => Self::FALL_SPEED,
         };
 
@@ -146,7 +151,7 @@ impl JumpingEnemy {
         let accel_y = if self.aerial_state == AerialState::Jumping(0.0) {
             f32::INFINITY // this means velocity override
         } else {
-            Self::ACCEL_Y
+            tuning.jumping_gravity
         } * delta_time;
 
         // move player to match target velocity y
@@ -162,7 +167,7 @@ impl JumpingEnemy {
                 Self::VELOCITY_ON_GROUND_MULTIPLIER_X,
             AerialState::Falling | AerialState::Jumping(_) =>
                 1.0,
-        } * self.direction.to_f32() * Self::MOVE_SPEED_X;
+        } * self.direction.to_f32() * tuning.jumping_move_speed;
 
         // find acceleration in x
         let accel_x = match self.aerial_state {
@@ -189,16 +194,23 @@ impl JumpingEnemy {
                 if is_goomba_stomping(&player.physics.bounding_box, &self.physics.bounding_box) {
                     self.alive = false;
                     player.physics.velocity.y = -6.0;
+                    player.refresh_dash();
                     // insert any other blessings/curses here
-                } else {
-                    println!("player lose");
-                    player.alive = false;
+                } else if player.damage() {
                     return Some(GameStateChange::PlayerLose)
                 }
             }
             None
         });
     }
+
+    // applies the effect of a projectile that hit this enemy
+    pub fn hit(&mut self, effect: ProjectileEffect) {
+        match effect {
+            ProjectileEffect::Knockback => self.curse_state.can_jump = 0.0,
+            ProjectileEffect::Slow => (),
+        }
+    }
 }
 
 impl GameObject for JumpingEnemy {
@@ -239,16 +251,6 @@ impl Physics for JumpingEnemy {
             self.direction = self.direction.reverse();
         }
 
-        let projectile_interaction = types.iter().find(|(t, _)| match t {
-            PhysObjType::Projectile(ProjectileType::Basic) => true,
-            PhysObjType::Projectile(ProjectileType::Slowing) => true,
-            _ => false
-        });
-        match projectile_interaction {
-            Some((PhysObjType::Projectile(ProjectileType::Basic), _)) => self.curse_state.can_jump = 0.0,
-            Some((PhysObjType::Projectile(ProjectileType::Slowing), _)) => (),
-            _ => ()
-        }
         self.resolved_on.extend(types.into_iter().map(|(_, id)| id));
         delta + resolve
     }
@@ -162,9 +162,10 @@ pub fn simulate<F: FnMut(PhysicsID, Vector2<f32>, Vector2<f32>, &mut PhysicsObje
 
                 let obj = objects.get_mut(&id).unwrap();
                 resolve(id, delta, best_resolve, obj, overlap_types);
-                overlappers.iter().for_each(|id| {
-                    objects.get_mut(id).map(|obj| {
-                        resolve(*id, Vector2::zero(), Vector2::zero(), obj, vec![(obj_typ, *id)]);
+                // let everything we ran into know that we (the mover) touched them
+                overlappers.iter().for_each(|other_id| {
+                    objects.get_mut(other_id).map(|obj| {
+                        resolve(*other_id, Vector2::zero(), Vector2::zero(), obj, vec![(obj_typ, id)]);
                     });
                 });
             }
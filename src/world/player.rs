@@ -1,12 +1,11 @@
 use std::collections::HashSet;
 
-use cgmath::{Vector2, Vector4};
+use cgmath::{Vector2, Vector4, Zero, InnerSpace};
 use uuid::Uuid;
-use winit::event::VirtualKeyCode;
 
-use crate::{bounding_box::BoundingBox, InputState};
+use crate::{bounding_box::BoundingBox, InputState, input::Action, tuning::Tuning, animation::Animator};
 
-use super::{PhysicsObject, GameObject, IDObject, Physics, physics::PhysObjType, projectile::{Projectile, ProjectileType}};
+use super::{PhysicsObject, GameObject, IDObject, Physics, physics::PhysObjType, projectile::Projectile, spell::{Spellbook, SpellDef, aim_direction}};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Direction {
@@ -21,6 +20,23 @@ impl Direction {
             Direction::Right => 1.0
         }
     }
+
+    pub fn from_f32(x_dir: f32) -> Option<Direction> {
+        if x_dir < 0.0 {
+            Some(Direction::Left)
+        } else if x_dir > 0.0 {
+            Some(Direction::Right)
+        } else {
+            None
+        }
+    }
+
+    pub fn reverse(&self) -> Self {
+        match *self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -40,72 +56,101 @@ pub enum HorizontalState {
     Stopped
 }
 
-pub struct DashInfo {
-    pub num_dashes_left: u32,
-    pub dashing_time_elapsed: f32,
-    pub cooldown_time_elapsed: f32,
-    pub velocity: Vector2<f32>,
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DashState {
+    Ready,
+    // t is the time since the dash started
+    Dashing { dir: Vector2<f32>, t: f32 },
+    // brief stop in the air at the end of a dash, t is the time since the hang started
+    Hang { dir: Vector2<f32>, t: f32 },
+    Cooldown { t: f32 },
 }
 
-impl DashInfo {
-    const NUM_DASHES: u32 = 1;
-
-    const DASH_SPEED: f32 = 20.0;
-    const DASH_ACCEL: f32 = 50.0;
-    const DASH_SLOWDOWN_MULTIPLIER: f32 = 10.0;
-    const DASH_DURATION: f32 = 0.2;
-    // DASH_PEAK begins dash slowdown 
-    const DASH_PEAK: f32 = 0.1;
-    // DASH_HANG_TIME comes after dash peak but before end of dash_duration 
-    const DASH_HANG_TIME: f32 = 0.05;
-    const DASH_COOLDOWN: f32 = 0.1;
-
-    pub fn new() -> Self {
-        Self {   
-            num_dashes_left: 1,
-            dashing_time_elapsed: 0.0,
-            cooldown_time_elapsed: 0.0,
-            velocity: Vector2::new(0.0, 0.0) 
+impl DashState {
+    // advances the state by delta_time, request is the direction of a dash the player asked to start
+    pub fn next(self, delta_time: f32, request: Option<Vector2<f32>>, tuning: &Tuning) -> DashState {
+        match self {
+            DashState::Ready => match request {
+                Some(dir) => DashState::Dashing { dir, t: 0.0 },
+                None => DashState::Ready,
+            },
+            DashState::Dashing { dir, t } if t + delta_time >= tuning.dash_duration =>
+                DashState::Hang { dir, t: t + delta_time - tuning.dash_duration },
+            DashState::Dashing { dir, t } => DashState::Dashing { dir, t: t + delta_time },
+            DashState::Hang { t, .. } if t + delta_time >= tuning.dash_hang_time =>
+                DashState::Cooldown { t: t + delta_time - tuning.dash_hang_time },
+            DashState::Hang { dir, t } => DashState::Hang { dir, t: t + delta_time },
+            DashState::Cooldown { t } if t + delta_time >= tuning.dash_cooldown => DashState::Ready,
+            DashState::Cooldown { t } => DashState::Cooldown { t: t + delta_time },
         }
-    } 
-
-    pub fn has_dashes_remaining(&self) -> bool {
-        self.num_dashes_left > 0
     }
 
-    pub fn is_dashing(&self) -> bool {
-        self.dashing_time_elapsed > 0.0 && !self.is_in_cooldown()
+    // the velocity the dash forces on the player, None when normal movement applies
+    pub fn velocity(&self, tuning: &Tuning) -> Option<Vector2<f32>> {
+        match *self {
+            DashState::Dashing { dir, t } => {
+                // the dash slows down from its peak to the end speed
+                let speed = if t < tuning.dash_peak {
+                    tuning.dash_speed
+                } else {
+                    let slowdown = (t - tuning.dash_peak) / (tuning.dash_duration - tuning.dash_peak);
+                    tuning.dash_speed + (tuning.dash_end_speed - tuning.dash_speed) * f32::min(slowdown, 1.0)
+                };
+                Some(dir * speed)
+            },
+            DashState::Hang { .. } => Some(Vector2::zero()),
+            DashState::Ready | DashState::Cooldown { .. } => None,
+        }
     }
 
-    pub fn is_before_peak(&self) -> bool {
-        self.dashing_time_elapsed >= 0.0 && self.dashing_time_elapsed < DashInfo::DASH_PEAK
+    pub fn is_dashing(&self) -> bool {
+        matches!(self, DashState::Dashing { .. } | DashState::Hang { .. })
     }
+}
 
-    pub fn is_after_peak(&self) -> bool {
-        self.is_dashing() && !self.is_before_peak()
+// resolves held direction keys into a normalized dash direction, opposite keys cancel out
+// and no direction at all dashes the way the player is facing
+pub fn dash_direction(up: bool, left: bool, down: bool, right: bool, facing: Direction) -> Vector2<f32> {
+    let axis = |neg: bool, pos: bool| match (neg, pos) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    let dir = Vector2::new(axis(left, right), axis(up, down));
+    if dir.is_zero() {
+        Vector2::new(facing.value(), 0.0)
+    } else {
+        dir.normalize()
     }
+}
 
-    pub fn is_hanging(&self) -> bool {
-        self.is_dashing() && self.dashing_time_elapsed > DashInfo::DASH_DURATION - DashInfo::DASH_HANG_TIME 
-    }
+pub struct DashInfo {
+    pub state: DashState,
+    pub num_dashes_left: u32,
+}
 
-    pub fn is_dashing_vertically(&self) -> bool {
-        self.is_dashing() && self.velocity.y != 0.0
-    }
+impl DashInfo {
+    const NUM_DASHES: u32 = 1;
 
-    pub fn is_dashing_horizontally(&self) -> bool {
-        self.is_dashing() && self.velocity.x != 0.0
+    pub fn new() -> Self {
+        Self {
+            state: DashState::Ready,
+            num_dashes_left: Self::NUM_DASHES,
+        }
     }
 
-    pub fn is_in_cooldown(&self) -> bool {
-        self.cooldown_time_elapsed > 0.0
+    pub fn refresh(&mut self) {
+        self.num_dashes_left = Self::NUM_DASHES;
     }
 
-    pub fn update_cooldown(&mut self, delta_time: f32) {
-        self.cooldown_time_elapsed += delta_time;
-        if self.cooldown_time_elapsed >= DashInfo::DASH_COOLDOWN {
-            self.cooldown_time_elapsed = 0.0;
+    // request is only honored when a dash is available
+    pub fn update(&mut self, delta_time: f32, request: Option<Vector2<f32>>, tuning: &Tuning) {
+        let request = request.filter(|_| self.num_dashes_left > 0);
+        let next = self.state.next(delta_time, request, tuning);
+        if !self.state.is_dashing() && next.is_dashing() {
+            self.num_dashes_left -= 1;
         }
+        self.state = next;
     }
 }
 
@@ -117,24 +162,27 @@ pub struct Player {
     pub aerial_state: AerialState,
     pub horizontal_state: HorizontalState,
     pub alive: bool,
-    pub current_projectile: usize,
+    // ignores all damage, toggled with the god command
+    pub god_mode: bool,
+    pub spellbook: Spellbook,
+    coyote_timer: f32,
+    jump_buffer_timer: f32,
+    // side of the player a wall was touched on during the last physics step
+    pub wall_contact: Option<Direction>,
+    last_wall: Direction,
+    wall_timer: f32,
+    wall_jump_lockout: f32,
+    pub animator: Animator,
 }
 
 impl Player {
-    const JUMP_SPEED: f32 = 4.0;
-    const JUMP_HOLD_TIMER_MAX: f32 = 0.3;
-    const JUMP_HOLD_TIMER_MIN: f32 = 0.15;
+    // movement values live in Tuning so they can be changed while playing
 
-    const FALL_SPEED: f32 = 5.0;
-    const PLAYER_ACCEL_Y: f32 = 22.0;
-
-    const PLAYER_MOVE_SPEED_X: f32 = 7.0;
-    const PLAYER_ACCEL_X: f32 = 10.0;
-    const PLAYER_ON_GROUND_MULTIPLIER_X: f32 = 2.0;
-    const PLAYER_TURNAROUND_MULTIPLIER_X: f32 = 14.0; 
+    // how long after leaving a wall a wall jump is still allowed
+    const WALL_JUMP_GRACE_TIME: f32 = 0.1;
 
     // initialize with position, scale, and color -- velocity and acceleration should be 0 when starting
-    pub fn new(position: Vector2<f32>) -> Self {
+    pub fn new(position: Vector2<f32>, spells: Vec<SpellDef>) -> Self {
         let physics = PhysicsObject {
             bounding_box: BoundingBox::new(position, 0.6, 1.0),
             velocity: Vector2::new(0.0, 0.0),
@@ -151,184 +199,129 @@ impl Player {
             aerial_state: AerialState::Falling,
             horizontal_state: HorizontalState::Stopped,
             alive: true,
-            current_projectile: 1,
+            god_mode: false,
+            spellbook: Spellbook::new(spells),
+            coyote_timer: 0.0,
+            jump_buffer_timer: 0.0,
+            wall_contact: None,
+            last_wall: Direction::Right,
+            wall_timer: 0.0,
+            wall_jump_lockout: 0.0,
+            animator: Animator::new("player.idle"),
         }
     }
 
-    pub fn update_dash(&mut self, delta_time: f32, input_state: &InputState) {
-        let accel_scalar = if self.dash_info.dashing_time_elapsed == 0.0 {
-            f32::INFINITY
-            // DashInfo::DASH_ACCEL
-        } else if self.dash_info.is_after_peak() {
-            DashInfo::DASH_ACCEL * DashInfo::DASH_SLOWDOWN_MULTIPLIER
-        // } 
-        // else if self.dash_info.is_hanging() {
-        //     0.0
+    // the animation clip for what the player is doing, most important first
+    pub fn clip(&self) -> &'static str {
+        if !self.alive {
+            "player.die"
+        } else if self.dash_info.state.is_dashing() {
+            "player.dash"
         } else {
-            DashInfo::DASH_ACCEL
-        } * delta_time;
+            match self.aerial_state {
+                AerialState::Jumping(_) => "player.jump",
+                AerialState::Falling => "player.fall",
+                AerialState::OnGround => match self.horizontal_state {
+                    HorizontalState::Stopped | HorizontalState::Stopping => "player.idle",
+                    _ => "player.run",
+                },
+            }
+        }
+    }
 
-        let accel = match (input_state.key_pos_edge.contains(&VirtualKeyCode::W), 
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::A),
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::S),
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::D), 
-                        input_state.key_down.contains(&VirtualKeyCode::W), 
-                        input_state.key_down.contains(&VirtualKeyCode::A),
-                        input_state.key_down.contains(&VirtualKeyCode::S),
-                        input_state.key_down.contains(&VirtualKeyCode::D)) {
-            // left or right
-            (false, true, false, _, _, _, _, _) 
-            | (_, _, _, _, false, true, false, _) 
-            | (false, _, false, true, _, _, _, _) 
-            | (_, _, _, _, false, _, false, true) => Vector2::new(accel_scalar, 0.0),
-
-            // up or down
-            (_, false, true, false, _, _, _, _)
-            | (_, _, _, _, _, false, true, false) 
-            | (true, false, _, false, _, _, _, _) 
-            | (_, _, _, _, true, false, _, false) => Vector2::new(0.0, accel_scalar),
-
-            // diagonals are sqrt 2 to feel good? (circular)
-            (false, true, true, false, _, _, _, _)
-            | (_, _, _, _, false, true, true, false)
-            | (false, false, true, true, _, _, _, _)
-            | (_, _, _, _, false, false, true, true)
-            | (true, true, false, false, _, _, _, _) 
-            | (_, _, _, _, true, true, false, false)
-            | (true, false, false, true, _, _, _, _)
-            | (_, _, _, _, true, false, false, true)  => Vector2::new(accel_scalar / f32::sqrt(2.0), accel_scalar / f32::sqrt(2.0)),
-
-            (_, _, _, _, _, _, _, _) => Vector2::new(accel_scalar, 0.0),
-        };
+    pub fn update_dash(&mut self, delta_time: f32, input_state: &InputState, tuning: &Tuning) {
+        if self.aerial_state == AerialState::OnGround && !self.dash_info.state.is_dashing() {
+            self.dash_info.refresh();
+        }
 
-        let target_speed = if self.dash_info.is_before_peak() {
-            DashInfo::DASH_SPEED
+        let request = if input_state.actions.pressed(Action::Dash) {
+            Some(dash_direction(
+                input_state.actions.is_down(Action::MoveUp),
+                input_state.actions.is_down(Action::MoveLeft),
+                input_state.actions.is_down(Action::MoveDown),
+                input_state.actions.is_down(Action::MoveRight),
+                self.direction))
         } else {
-            0.0
+            None
         };
+        self.dash_info.update(delta_time, request, tuning);
 
-        let target_vel = match (input_state.key_pos_edge.contains(&VirtualKeyCode::W), 
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::A),
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::S),
-                        input_state.key_pos_edge.contains(&VirtualKeyCode::D), 
-                        input_state.key_down.contains(&VirtualKeyCode::W), 
-                        input_state.key_down.contains(&VirtualKeyCode::A),
-                        input_state.key_down.contains(&VirtualKeyCode::S),
-                        input_state.key_down.contains(&VirtualKeyCode::D)) {
-            // left
-            (false, true, false, _, _, _, _, _)
-            | (_, _, _, _, false, true, false, _) => Vector2::new(-target_speed, 0.0),
-
-            // right
-            (false, _, false, true, _, _, _, _)
-            | (_, _, _, _, false, _, false, true) => Vector2::new(target_speed, 0.0),
-
-            // down
-            (_, false, true, false, _, _, _, _)
-            | (_, _, _, _, _, false, true, false) => Vector2::new(0.0, target_speed),
-            
-            // up
-            (true, false, _, false, _, _, _, _) 
-            | (_, _, _, _, true, false, _, false) => Vector2::new(0.0, -target_speed),
-
-            // down-left
-            (false, true, true, false, _, _, _, _)
-            | (_, _, _, _, false, true, true, false) => Vector2::new(-target_speed / f32::sqrt(2.0), target_speed / f32::sqrt(2.0)),
-            
-            // down-right
-            (false, false, true, true, _, _, _, _)
-            | (_, _, _, _, false, false, true, true) => Vector2::new(target_speed / f32::sqrt(2.0), target_speed / f32::sqrt(2.0)),
-            
-            // up-left
-            (true, true, false, false, _, _, _, _) 
-            | (_, _, _, _, true, true, false, false) => Vector2::new(-target_speed / f32::sqrt(2.0), -target_speed / f32::sqrt(2.0)),
-            
-            // up-right
-            (true, false, false, true, _, _, _, _)
-            | (_, _, _, _, true, false, false, true) => Vector2::new(target_speed / f32::sqrt(2.0), -target_speed / f32::sqrt(2.0)),
-            
-            // diagonals are sqrt 2 to feel good? (circular)
-
-            // default dash should be whichever way the character is facing
-            (_, _, _, _, _, _, _, _) => Vector2::new(target_speed * self.direction.value(), 0.0),
-        };
-
-        println!("dashing with velocity ({}, {}) at time {}", target_vel.x, target_vel.y, self.dash_info.dashing_time_elapsed);
-        
-        // "reset" velocities in the relevant directions if we just started dashing
-        // for diagonal, these should be halved or set to some low value instead of completely zeroing out
-        self.physics.velocity.y = if target_vel.y == 0.0 {
-            0.0
-        } else { 
-            self.physics.velocity.y 
-        };
-        self.physics.velocity.x = if target_vel.x == 0.0 {
-            0.0
-        } else { 
-            self.physics.velocity.x 
-        };
-        
-
-        if f32::abs(self.physics.velocity.x - target_vel.x) < accel.x {
-            self.physics.velocity.x = target_vel.x;
-        } else {
-            self.physics.velocity.x += f32::signum(target_vel.x - self.physics.velocity.x) * accel.x;
-        }
-        if f32::abs(self.physics.velocity.y - target_vel.y) < accel.y {
-            self.physics.velocity.y = target_vel.y;
-        } else {
-            self.physics.velocity.y += f32::signum(target_vel.y - self.physics.velocity.y) * accel.y;
+        if let Some(velocity) = self.dash_info.state.velocity(tuning) {
+            self.physics.velocity = velocity;
         }
+    }
 
-        // mimic the code directly above but for the y directions
-        // if f32::abs(self.physics.velocity.x - target_vel.x) < accel.x {
-        //     self.physics.velocity.x = target_vel.x;
-        // } else {
-        //     self.physics.velocity.x += f32::signum(target_vel.x - self.physics.velocity.x) * accel.x;
-        // }
-        
-        self.dash_info.dashing_time_elapsed += delta_time;
-        if self.dash_info.dashing_time_elapsed >= DashInfo::DASH_DURATION + DashInfo::DASH_HANG_TIME {
-            self.dash_info.velocity.x = 0.0; 
-            self.dash_info.velocity.y = 0.0;
-            self.dash_info.dashing_time_elapsed = 0.0;
+    // enemies can be passed through without harm while dashing
+    pub fn is_invulnerable(&self) -> bool {
+        self.dash_info.state.is_dashing()
+    }
 
-            self.dash_info.cooldown_time_elapsed += delta_time;
+    // kills the player unless invulnerable, returns true if it did
+    pub fn damage(&mut self) -> bool {
+        if self.is_invulnerable() || self.god_mode || !self.alive {
+            return false
         }
+        self.alive = false;
+        true
     }
 
-    // creates and returns a projectile to add to the game world
-    pub fn shoot(&self) -> () {
-        // // Wand is in front of the player
-        // let WAND_LOCATION: Vector2<f32> = self.physics.bounding_box.center + self.direction;
-        // return Projectile::new(
-        //     WAND_LOCATION,
-        //     Vector2::new(0.25, 0.25),
-        //     Vector4::new(0.0, 0.0, 0.0, 0.0),
-        //     self.direction
-        // )
+    // gives back the dash, e.g. after stomping an enemy
+    pub fn refresh_dash(&mut self) {
+        self.dash_info.refresh();
     }
 
-    pub fn update(&mut self, delta_time: f32, input_state: &InputState) {
-        // update projectile type
-        self.current_projectile = match (input_state.key_pos_edge.contains(&VirtualKeyCode::Key1),
-                                        input_state.key_pos_edge.contains(&VirtualKeyCode::Key2),
-                                        input_state.key_down.contains(&VirtualKeyCode::Key1),
-                                        input_state.key_down.contains(&VirtualKeyCode::Key2)) {
-            (true, _, _, _)
-            | (_, _, true, _) => 1,
-
-            (_, true, _, _)
-            | (_, _, _, true) => 1,
+    pub fn is_wall_sliding(&self, input_state: &InputState) -> bool {
+        let holding_towards = |wall: Direction| match wall {
+            Direction::Left => input_state.actions.is_down(Action::MoveLeft),
+            Direction::Right => input_state.actions.is_down(Action::MoveRight),
+        };
+        self.aerial_state == AerialState::Falling
+            && self.physics.velocity.y > 0.0
+            && self.wall_contact.map_or(false, holding_towards)
+    }
 
-            (_, _, _, _) => self.current_projectile
+    // casts the current spell towards target if it is off cooldown and affordable,
+    // returning the projectiles to add to the game world
+    pub fn shoot(&mut self, target: Vector2<f32>) -> Vec<Projectile> {
+        let origin = self.physics.bounding_box.center;
+        let dir = match aim_direction(origin, target) {
+            Some(dir) => dir,
+            None => return vec![],
         };
+        match self.spellbook.try_cast() {
+            Some(spell) => spell.directions(dir).into_iter().map(|dir|
+                Projectile::new(origin, dir * spell.speed, spell.projectile, PhysObjType::Enemy)
+            ).collect(),
+            None => vec![],
+        }
+    }
 
+    pub fn update(&mut self, delta_time: f32, input_state: &InputState, tuning: &Tuning) {
+        // update selected spell
+        let actions = &input_state.actions;
+        if let Some(index) = Action::spells().iter().position(|action| actions.pressed(*action)) {
+            self.spellbook.select(index);
+        }
+        if input_state.mouse_wheel > 0.0 || actions.pressed(Action::PrevSpell) {
+            self.spellbook.cycle(-1);
+        } else if input_state.mouse_wheel < 0.0 || actions.pressed(Action::NextSpell) {
+            self.spellbook.cycle(1);
+        }
+        self.spellbook.update(delta_time);
 
-        self.direction = match (input_state.key_pos_edge.contains(&VirtualKeyCode::A),
-                                input_state.key_pos_edge.contains(&VirtualKeyCode::D),
-                                input_state.key_down.contains(&VirtualKeyCode::A),
-                                input_state.key_down.contains(&VirtualKeyCode::D)) {
+        // horizontal input, overridden to keep moving away from the wall right after a wall jump
+        self.wall_jump_lockout = f32::max(0.0, self.wall_jump_lockout - delta_time);
+        let (left_edge, right_edge, left_down, right_down) = if self.wall_jump_lockout > 0.0 {
+            let away_left = self.direction == Direction::Left;
+            (false, false, away_left, !away_left)
+        } else {
+            (input_state.actions.pressed(Action::MoveLeft),
+             input_state.actions.pressed(Action::MoveRight),
+             input_state.actions.is_down(Action::MoveLeft),
+             input_state.actions.is_down(Action::MoveRight))
+        };
+
+        self.direction = match (left_edge, right_edge, left_down, right_down) {
             (true, _, _, _) 
             | (_, _, true, _) => Direction::Left,
             
@@ -338,19 +331,65 @@ impl Player {
             (_,_,_,_) => self.direction
         };
         
+        // remember jump presses for a moment so pressing just before landing still jumps
+        if input_state.actions.pressed(Action::Jump) {
+            self.jump_buffer_timer = tuning.player_jump_buffer;
+        } else {
+            self.jump_buffer_timer = f32::max(0.0, self.jump_buffer_timer - delta_time);
+        }
+
+        // coyote time: keep allowing a jump shortly after walking off a ledge
+        if self.aerial_state == AerialState::OnGround {
+            self.coyote_timer = tuning.player_coyote_time;
+        } else {
+            self.coyote_timer = f32::max(0.0, self.coyote_timer - delta_time);
+        }
+        let can_jump = match self.aerial_state {
+            AerialState::OnGround => true,
+            AerialState::Falling => self.coyote_timer > 0.0,
+            AerialState::Jumping(_) => false,
+        };
+        let start_jump = can_jump && self.jump_buffer_timer > 0.0;
+        if start_jump {
+            self.jump_buffer_timer = 0.0;
+            self.coyote_timer = 0.0;
+        }
+
+        // wall jump: kick off a wall we touched very recently while in the air
+        self.wall_timer = f32::max(0.0, self.wall_timer - delta_time);
+        if let Some(wall) = self.wall_contact {
+            self.last_wall = wall;
+            self.wall_timer = Player::WALL_JUMP_GRACE_TIME;
+        }
+        let wall_jump = !start_jump
+            && self.aerial_state != AerialState::OnGround
+            && self.jump_buffer_timer > 0.0
+            && self.wall_timer > 0.0;
+        if wall_jump {
+            let away = self.last_wall.reverse();
+            self.jump_buffer_timer = 0.0;
+            self.wall_timer = 0.0;
+            self.physics.velocity.x = away.value() * tuning.player_wall_jump_speed;
+            self.direction = away;
+            self.horizontal_state = match away {
+                Direction::Left => HorizontalState::MovingLeft,
+                Direction::Right => HorizontalState::MovingRight,
+            };
+            self.wall_jump_lockout = tuning.player_wall_jump_lockout;
+        }
 
         // change jump state
-        self.aerial_state = match (input_state.key_pos_edge.contains(&VirtualKeyCode::Space),
-                            input_state.key_down.contains(&VirtualKeyCode::Space),
+        self.aerial_state = match (start_jump || wall_jump,
+                            input_state.actions.is_down(Action::Jump),
                             self.aerial_state.clone()) {
             // case where we start jumping
-            (true, _, AerialState::OnGround) =>
+            (true, _, _) =>
                 AerialState::Jumping(0.0),
 
             // case where we keep jumping
-            (_, false, AerialState::Jumping(timer)) if timer < Player::JUMP_HOLD_TIMER_MIN =>
+            (_, false, AerialState::Jumping(timer)) if timer < tuning.player_jump_hold_min =>
                 AerialState::Jumping(timer + delta_time),
-            (_, true, AerialState::Jumping(timer)) if timer < Player::JUMP_HOLD_TIMER_MAX =>
+            (_, true, AerialState::Jumping(timer)) if timer < tuning.player_jump_hold_max =>
                 AerialState::Jumping(timer + delta_time),
 
             // go from jumping to falling
@@ -361,10 +400,10 @@ impl Player {
             (_, _, state) => state,
         };
 
-        self.horizontal_state = match (input_state.key_pos_edge.contains(&VirtualKeyCode::A),
-                                input_state.key_pos_edge.contains(&VirtualKeyCode::D),
-                                input_state.key_down.contains(&VirtualKeyCode::A),
-                                input_state.key_down.contains(&VirtualKeyCode::D),
+        self.horizontal_state = match (left_edge,
+                                right_edge,
+                                left_down,
+                                right_down,
                                 self.horizontal_state.clone() 
         ) {
             //  BUG: this still lets the player vibrate back and forth slowly towards one direction
@@ -405,15 +444,25 @@ impl Player {
 
         // find target y velocity
         let target_vel_y = match self.aerial_state {
-            AerialState::Jumping(_) => -Player::JUMP_SPEED,
-            _ => Player::FALL_SPEED,
+            AerialState::Jumping(_) => -tuning.player_jump_speed,
+            _ => tuning.player_fall_speed,
+        };
+
+        // hang at the top of a held jump, then fall faster than we rose
+        let gravity_multiplier = match self.aerial_state {
+            AerialState::Falling if f32::abs(self.physics.velocity.y) < tuning.player_apex_threshold
+                && input_state.actions.is_down(Action::Jump) =>
+                tuning.player_apex_gravity,
+            AerialState::Falling if self.physics.velocity.y > 0.0 =>
+                tuning.player_fall_gravity,
+            _ => 1.0,
         };
 
         // find acceleration in y
         let accel_y = if self.aerial_state == AerialState::Jumping(0.0) {
             f32::INFINITY // this means velocity override
         } else {
-            Player::PLAYER_ACCEL_Y
+            tuning.player_gravity * gravity_multiplier
         } * delta_time;
 
         // move player to match target velocity y
@@ -423,22 +472,27 @@ impl Player {
             self.physics.velocity.y += f32::signum(target_vel_y - self.physics.velocity.y) * accel_y;
         }
 
+        // slide slowly down walls we are pushing into
+        if self.is_wall_sliding(input_state) {
+            self.physics.velocity.y = f32::min(self.physics.velocity.y, tuning.player_wall_slide_speed);
+        }
+
         // find player's ability to self-accelerate x
         // when aerial state is OnGround, physics should feel snappier -- higher acceleration
         let accel_x = if self.aerial_state == AerialState::OnGround 
          && (self.horizontal_state == HorizontalState::TurningLeft 
             || self.horizontal_state == HorizontalState::TurningRight 
             || self.horizontal_state == HorizontalState::Stopping){
-            delta_time * Player::PLAYER_ACCEL_X * Player::PLAYER_ON_GROUND_MULTIPLIER_X   
+            delta_time * tuning.player_accel_x * tuning.player_ground_accel_multiplier   
         } else {
-            delta_time * Player::PLAYER_ACCEL_X
+            delta_time * tuning.player_accel_x
         };
 
         let target_vel_x = match self.horizontal_state {
-            HorizontalState::MovingLeft => -Player::PLAYER_MOVE_SPEED_X,
-            HorizontalState::TurningLeft => -Player::PLAYER_MOVE_SPEED_X * Player::PLAYER_TURNAROUND_MULTIPLIER_X,
-            HorizontalState::MovingRight => Player::PLAYER_MOVE_SPEED_X,
-            HorizontalState::TurningRight => Player::PLAYER_MOVE_SPEED_X * Player::PLAYER_TURNAROUND_MULTIPLIER_X,
+            HorizontalState::MovingLeft => -tuning.player_move_speed,
+            HorizontalState::TurningLeft => -tuning.player_move_speed * tuning.player_turnaround_multiplier,
+            HorizontalState::MovingRight => tuning.player_move_speed,
+            HorizontalState::TurningRight => tuning.player_move_speed * tuning.player_turnaround_multiplier,
             _ => 0.0
         };
 
@@ -473,21 +527,7 @@ impl Player {
         };
 
 
-        // handle dash stuff here
-        // is this the best place to handle dash?
-
-        if input_state.key_pos_edge.contains(&VirtualKeyCode::E) 
-            && !(self.dash_info.is_dashing() || self.dash_info.is_in_cooldown())
-            && self.dash_info.has_dashes_remaining() {
-            self.dash_info.num_dashes_left -= 1;
-            self.update_dash(delta_time, input_state);
-        } else if self.dash_info.is_dashing() {
-            self.update_dash(delta_time, input_state);
-        } else if self.dash_info.is_in_cooldown() {
-            self.dash_info.update_cooldown(delta_time);
-        } else if self.aerial_state == AerialState::OnGround {
-            self.dash_info.num_dashes_left = DashInfo::NUM_DASHES;
-        }
+        self.update_dash(delta_time, input_state, tuning);
     }
 }
 
@@ -509,6 +549,7 @@ impl Physics for Player {
         if self.aerial_state == AerialState::OnGround {
             self.aerial_state = AerialState::Falling;
         }
+        self.wall_contact = None;
     }
 
     fn resolve(&mut self, _: Uuid, delta: Vector2<f32>, resolve: Vector2<f32>, types: Vec<(PhysObjType, Uuid)>) -> Vector2<f32> {
@@ -526,6 +567,10 @@ impl Physics for Player {
             // horizontal collision
             self.physics.velocity.x = 0.0;
             self.horizontal_state = HorizontalState::Stopped;
+            if types.iter().any(|(typ, _)| *typ == PhysObjType::Wall) {
+                // being pushed left means the wall is on our right
+                self.wall_contact = Direction::from_f32(-resolve.x);
+            }
         }
         delta + resolve
     }
@@ -535,3 +580,123 @@ impl Physics for Player {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use crate::world::spell::default_spells;
+    use super::*;
+
+    fn close(a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        (a - b).magnitude() < 1e-5
+    }
+
+    #[test]
+    fn dash_direction_in_all_eight_directions() {
+        let d = std::f32::consts::FRAC_1_SQRT_2;
+        // up, left, down, right
+        let cases = [
+            ((true, false, false, false), Vector2::new(0.0, -1.0)),
+            ((false, false, true, false), Vector2::new(0.0, 1.0)),
+            ((false, true, false, false), Vector2::new(-1.0, 0.0)),
+            ((false, false, false, true), Vector2::new(1.0, 0.0)),
+            ((true, true, false, false), Vector2::new(-d, -d)),
+            ((true, false, false, true), Vector2::new(d, -d)),
+            ((false, true, true, false), Vector2::new(-d, d)),
+            ((false, false, true, true), Vector2::new(d, d)),
+        ];
+        for ((up, left, down, right), expected) in cases {
+            let dir = dash_direction(up, left, down, right, Direction::Left);
+            assert!(close(dir, expected), "{:?} for {:?}", dir, (up, left, down, right));
+        }
+    }
+
+    #[test]
+    fn dash_direction_without_input_uses_facing() {
+        assert!(close(dash_direction(false, false, false, false, Direction::Left), Vector2::new(-1.0, 0.0)));
+        assert!(close(dash_direction(false, false, false, false, Direction::Right), Vector2::new(1.0, 0.0)));
+        // opposite keys cancel out
+        assert!(close(dash_direction(true, true, true, true, Direction::Right), Vector2::new(1.0, 0.0)));
+    }
+
+    #[test]
+    fn dash_state_goes_through_every_stage() {
+        let tuning = Tuning::default();
+        let dir = Vector2::new(1.0, 0.0);
+        let step = 0.01;
+        let mut state = DashState::Ready.next(step, None, &tuning);
+        assert_eq!(state, DashState::Ready);
+
+        state = state.next(step, Some(dir), &tuning);
+        assert!(matches!(state, DashState::Dashing { .. }));
+        let mut time = 0.0;
+        while matches!(state, DashState::Dashing { .. }) {
+            state = state.next(step, None, &tuning);
+            time += step;
+        }
+        assert!(matches!(state, DashState::Hang { .. }));
+        assert!((time - tuning.dash_duration).abs() <= step * 1.5);
+
+        while matches!(state, DashState::Hang { .. }) {
+            state = state.next(step, None, &tuning);
+        }
+        assert!(matches!(state, DashState::Cooldown { .. }));
+        // requests during the cooldown are ignored
+        state = state.next(step, Some(dir), &tuning);
+        assert!(matches!(state, DashState::Cooldown { .. }));
+
+        while matches!(state, DashState::Cooldown { .. }) {
+            state = state.next(step, None, &tuning);
+        }
+        assert_eq!(state, DashState::Ready);
+    }
+
+    #[test]
+    fn refresh_rearms_during_cooldown() {
+        let tuning = Tuning::default();
+        let mut dash = DashInfo::new();
+        dash.update(0.01, Some(Vector2::new(1.0, 0.0)), &tuning);
+        assert_eq!(dash.num_dashes_left, 0);
+        let wait_out = |dash: &mut DashInfo| for _ in 0..1000 {
+            dash.update(0.01, None, &tuning);
+            if !matches!(dash.state, DashState::Dashing { .. } | DashState::Hang { .. }) {
+                break
+            }
+        };
+        wait_out(&mut dash);
+        assert!(matches!(dash.state, DashState::Cooldown { .. }));
+
+        dash.refresh();
+        assert_eq!(dash.num_dashes_left, 1);
+        for _ in 0..1000 {
+            if dash.state == DashState::Ready {
+                break
+            }
+            dash.update(0.01, None, &tuning);
+        }
+        dash.update(0.01, Some(Vector2::new(0.0, -1.0)), &tuning);
+        assert!(dash.state.is_dashing());
+
+        // without a refresh the next dash isn't available
+        wait_out(&mut dash);
+        for _ in 0..1000 {
+            dash.update(0.01, None, &tuning);
+        }
+        dash.update(0.01, Some(Vector2::new(1.0, 0.0)), &tuning);
+        assert_eq!(dash.state, DashState::Ready);
+    }
+
+    #[test]
+    fn invulnerable_only_while_dashing() {
+        let mut player = Player::new(Vector2::new(0.0, 0.0), default_spells());
+        assert!(!player.is_invulnerable());
+        let dir = Vector2::new(1.0, 0.0);
+        player.dash_info.state = DashState::Dashing { dir, t: 0.0 };
+        assert!(player.is_invulnerable());
+        player.dash_info.state = DashState::Hang { dir, t: 0.0 };
+        assert!(player.is_invulnerable());
+        player.dash_info.state = DashState::Cooldown { t: 0.0 };
+        assert!(!player.is_invulnerable());
+        player.dash_info.state = DashState::Ready;
+        assert!(!player.is_invulnerable());
+    }
+}
@@ -1,4 +1,6 @@
-use cgmath::{Vector2, InnerSpace};
+use std::collections::HashSet;
+
+use cgmath::{Vector2, InnerSpace, Zero, Rad, Basis2, Rotation, Rotation2};
 use uuid::Uuid;
 
 use crate::bounding_box::BoundingBox;
@@ -8,31 +10,134 @@ use super::{physics::{PhysicsObject, Physics, PhysObjType}, GameObject, IDObject
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ProjectileType {
     Basic,
-    Slowing
+    Slowing,
+    Bouncing,
+    Seeking,
+    Piercing,
+    Bomb,
 }
 impl ProjectileType {
     pub fn all() -> Vec<ProjectileType> {
         use ProjectileType::*;
-        vec![Basic, Slowing]
+        vec![Basic, Slowing, Bouncing, Seeking, Piercing, Bomb]
+    }
+
+    pub fn name(&self) -> &'static str {
+        use ProjectileType::*;
+        match *self {
+            Basic => "basic",
+            Slowing => "slowing",
+            Bouncing => "bouncing",
+            Seeking => "seeking",
+            Piercing => "piercing",
+            Bomb => "bomb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ProjectileType> {
+        ProjectileType::all().into_iter().find(|typ| typ.name() == name)
+    }
+
+    pub fn def(&self) -> ProjectileDef {
+        use ProjectileType::*;
+        let basic = ProjectileDef {
+            size: 0.2,
+            lifetime: 10.0,
+            gravity: 0.0,
+            bounces: 0,
+            pierce: 0,
+            homing: 0.0,
+            homing_range: 0.0,
+            explosion_radius: 0.0,
+            effect: ProjectileEffect::Knockback,
+        };
+        match *self {
+            Basic => basic,
+            Slowing => ProjectileDef {
+                effect: ProjectileEffect::Slow,
+                ..basic
+            },
+            Bouncing => ProjectileDef {
+                lifetime: 4.0,
+                gravity: 12.0,
+                bounces: 3,
+                ..basic
+            },
+            Seeking => ProjectileDef {
+                lifetime: 4.0,
+                homing: 6.0,
+                homing_range: 8.0,
+                effect: ProjectileEffect::Slow,
+                ..basic
+            },
+            Piercing => ProjectileDef {
+                size: 0.15,
+                lifetime: 2.0,
+                pierce: 3,
+                ..basic
+            },
+            Bomb => ProjectileDef {
+                size: 0.3,
+                lifetime: 3.0,
+                gravity: 10.0,
+                explosion_radius: 2.5,
+                ..basic
+            },
+        }
     }
 }
 
+// what a projectile does to an enemy it hits
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProjectileEffect {
+    Knockback,
+    Slow,
+}
+
+// behaviours are independent of each other so they can be combined freely per ProjectileType
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileDef {
+    pub size: f32,
+    // seconds before the projectile dies on its own
+    pub lifetime: f32,
+    // downward acceleration, 0 travels in a straight line
+    pub gravity: f32,
+    // number of wall hits survived by reflecting off the wall
+    pub bounces: u32,
+    // number of enemies passed through before dying on the next one
+    pub pierce: u32,
+    // max turn rate towards the nearest enemy in radians per second, 0 disables homing
+    pub homing: f32,
+    pub homing_range: f32,
+    // radius of the area effect applied when the projectile dies, 0 disables
+    pub explosion_radius: f32,
+    pub effect: ProjectileEffect,
+}
+
 #[derive(Debug)]
 pub struct Projectile {
     id: Uuid,
     physics: PhysicsObject,
     pub alive: bool,
     pub typ: ProjectileType,
+    pub def: ProjectileDef,
     collides_with: PhysObjType,
     timer: f32,
+    bounces_left: u32,
+    pierces_left: u32,
+    // enemies already hit, so a piercing projectile only affects each enemy once
+    hit: HashSet<Uuid>,
+    // enemies hit since the world last applied this projectile's effect
+    pub new_hits: Vec<Uuid>,
 }
 
 
 impl Projectile {
     // initialize with position, scale, and color -- velocity and acceleration should be 0 when starting
     pub fn new(position: Vector2<f32>, velocity: Vector2<f32>, typ: ProjectileType, collides_with: PhysObjType) -> Self {
+        let def = typ.def();
         let physics = PhysicsObject {
-            bounding_box: BoundingBox::new(position, 0.2, 0.2),
+            bounding_box: BoundingBox::new(position, def.size, def.size),
             velocity,
             can_move: true,
             typ: super::physics::PhysObjType::Projectile(typ),
@@ -44,19 +149,56 @@ impl Projectile {
             physics,
             alive: true,
             typ,
+            def,
             collides_with,
             timer: 0.0,
+            bounces_left: def.bounces,
+            pierces_left: def.pierce,
+            hit: HashSet::new(),
+            new_hits: vec![],
         }
     }
 
-    pub fn update(&mut self, delta_time: f32) {
+    pub fn position(&self) -> Vector2<f32> {
+        self.physics.bounding_box.center
+    }
+
+    pub fn velocity(&self) -> Vector2<f32> {
+        self.physics.velocity
+    }
+
+    // target is the position of the nearest enemy, used for homing
+    pub fn update(&mut self, delta_time: f32, target: Option<Vector2<f32>>) {
         self.timer += delta_time;
-        if self.timer > 10.0 {
+        if self.timer > self.def.lifetime {
             self.alive = false;
         }
+
+        self.physics.velocity.y += self.def.gravity * delta_time;
+
+        if let Some(target) = target {
+            if self.def.homing > 0.0 {
+                self.physics.velocity = steer_towards(
+                    self.physics.velocity,
+                    target - self.position(),
+                    self.def.homing * delta_time);
+            }
+        }
     }
 }
 
+// rotates velocity towards the direction of to_target by at most max_angle radians, keeping its speed
+pub fn steer_towards(velocity: Vector2<f32>, to_target: Vector2<f32>, max_angle: f32) -> Vector2<f32> {
+    if velocity.is_zero() || to_target.is_zero() {
+        return velocity
+    }
+    // signed angle from velocity to to_target
+    let angle = f32::atan2(velocity.perp_dot(to_target), velocity.dot(to_target));
+    let turn = f32::clamp(angle, -max_angle, max_angle);
+    let rotation: Basis2<f32> = Rotation2::from_angle(Rad(turn));
+    rotation.rotate_vector(velocity)
+}
+
 impl GameObject for Projectile {
 }
 
@@ -73,9 +215,29 @@ impl Physics for Projectile {
 
     fn resolve(&mut self, _: Uuid, delta: Vector2<f32>, resolve: Vector2<f32>, types: Vec<(PhysObjType, Uuid)>) -> Vector2<f32> {
         self.physics.bounding_box.add(delta + resolve);
-        if types.len() > 0 {
-            println!("Proj move {:?}, {:?}, {:?}", delta, resolve, types);
-            self.alive = false;
+
+        // walls push the projectile out, the resolve direction is the wall's normal
+        let hit_wall = types.iter().any(|(typ, _)| *typ == PhysObjType::Wall);
+        if hit_wall {
+            if self.bounces_left > 0 && !resolve.is_zero() {
+                self.bounces_left -= 1;
+                let normal = resolve.normalize();
+                let velocity = self.physics.velocity;
+                self.physics.velocity = velocity - normal * 2.0 * velocity.dot(normal);
+            } else {
+                self.alive = false;
+            }
+        }
+
+        for (typ, id) in types {
+            if typ != PhysObjType::Wall && self.hit.insert(id) {
+                self.new_hits.push(id);
+                if self.pierces_left > 0 {
+                    self.pierces_left -= 1;
+                } else {
+                    self.alive = false;
+                }
+            }
         }
         delta + resolve
     }
@@ -84,7 +246,6 @@ impl Physics for Projectile {
     }
 
     fn typ(&self) -> PhysObjType {
-        PhysObjType::Projectile(ProjectileType::Basic)
+        PhysObjType::Projectile(self.typ)
     }
 }
-
@@ -0,0 +1,100 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KillType {
+    Basic,
+    Jumping,
+}
+
+impl KillType {
+    pub fn points(&self) -> u32 {
+        match *self {
+            KillType::Basic => 100,
+            KillType::Jumping => 150,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ScoreKeeper {
+    pub score: u32,
+    // stomps chained without touching the ground
+    pub combo: u32,
+    pub best_combo: u32,
+    pub kills: u32,
+}
+
+impl ScoreKeeper {
+    const MAX_MULTIPLIER: u32 = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the multiplier the next stomp will score with
+    pub fn multiplier(&self) -> u32 {
+        u32::min(self.combo + 1, Self::MAX_MULTIPLIER)
+    }
+
+    // awards points for a stomp and grows the combo, returns the points awarded
+    pub fn stomp(&mut self, kill: KillType) -> u32 {
+        let points = kill.points() * self.multiplier();
+        self.combo += 1;
+        self.best_combo = u32::max(self.best_combo, self.combo);
+        self.kills += 1;
+        self.score += points;
+        points
+    }
+
+    // landing ends the combo
+    pub fn land(&mut self) {
+        self.combo = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::world::{World, stage::StageLayout, spell::default_spells};
+
+    use super::*;
+
+    #[test]
+    fn chained_stomps_multiply() {
+        let mut score = ScoreKeeper::new();
+        assert_eq!(score.stomp(KillType::Basic), 100);
+        assert_eq!(score.stomp(KillType::Jumping), 300);
+        assert_eq!(score.stomp(KillType::Basic), 300);
+        assert_eq!((score.score, score.combo, score.best_combo, score.kills), (700, 3, 3, 3));
+    }
+
+    #[test]
+    fn multiplier_is_capped() {
+        let mut score = ScoreKeeper::new();
+        for _ in 0..7 {
+            score.stomp(KillType::Basic);
+        }
+        assert_eq!(score.multiplier(), 8);
+        assert_eq!(score.stomp(KillType::Basic), 800);
+        assert_eq!(score.stomp(KillType::Basic), 800);
+        assert_eq!(score.best_combo, 9);
+    }
+
+    #[test]
+    fn landing_resets_the_combo_but_not_the_best() {
+        let mut score = ScoreKeeper::new();
+        score.stomp(KillType::Basic);
+        score.stomp(KillType::Basic);
+        score.land();
+        assert_eq!((score.combo, score.best_combo, score.multiplier()), (0, 2, 1));
+        assert_eq!(score.stomp(KillType::Basic), 100);
+    }
+
+    #[test]
+    fn carry_over_lands_and_counts_kills_from_the_new_level() {
+        let mut score = ScoreKeeper::new();
+        score.stomp(KillType::Basic);
+        score.stomp(KillType::Jumping);
+        let mut world = World::with_level(2, &StageLayout::default(), &default_spells());
+        world.carry_over(score);
+        assert_eq!((world.score.score, world.score.combo, world.score.kills), (400, 0, 2));
+        assert_eq!(world.level_kills(), 0);
+    }
+}
@@ -0,0 +1,275 @@
+use cgmath::{Vector2, InnerSpace, Rad, Basis2, Rotation, Rotation2};
+
+use crate::assets::Assets;
+use super::projectile::ProjectileType;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpellDef {
+    pub name: String,
+    pub projectile: ProjectileType,
+    pub speed: f32,
+    // total angle in radians that the projectiles fan out over
+    pub spread: f32,
+    pub count: u32,
+    pub cooldown: f32,
+    pub mana_cost: f32,
+}
+
+impl SpellDef {
+    // evenly fans count directions out around dir, dir is expected to be normalized
+    pub fn directions(&self, dir: Vector2<f32>) -> Vec<Vector2<f32>> {
+        if self.count <= 1 || self.spread == 0.0 {
+            return (0..self.count).map(|_| dir).collect()
+        }
+        let step = self.spread / (self.count - 1) as f32;
+        (0..self.count).map(|i| {
+            let angle = -self.spread / 2.0 + step * i as f32;
+            let rotation: Basis2<f32> = Rotation2::from_angle(Rad(angle));
+            rotation.rotate_vector(dir)
+        }).collect()
+    }
+}
+
+pub const PATH: &str = "spells.cfg";
+
+// each "spell <projectile> <speed> <spread> <count> <cooldown> <mana cost> <name>" adds a spell after the last,
+// the name is the rest of the line. blank lines and lines starting with # are skipped
+pub fn spells_from_config_str(config: &str) -> Result<Vec<SpellDef>, String> {
+    let mut spells = vec![];
+    for (number, line) in config.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let error = |message: &str| format!("line {}: {}", number + 1, message);
+        let amount = |value: &str| value.parse::<f32>().ok().filter(|value| value.is_finite() && *value >= 0.0)
+            .ok_or_else(|| error(&format!("{} is not a number of at least 0", value)));
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["spell", projectile, speed, spread, count, cooldown, mana_cost, ref name @ ..] if !name.is_empty() => {
+                let projectile = ProjectileType::from_name(projectile).ok_or_else(|| {
+                    let names: Vec<&str> = ProjectileType::all().iter().map(ProjectileType::name).collect();
+                    error(&format!("{} is not one of {}", projectile, names.join(", ")))
+                })?;
+                let count = count.parse::<u32>().ok().filter(|count| *count > 0)
+                    .ok_or_else(|| error(&format!("{} is not a positive count", count)))?;
+                spells.push(SpellDef {
+                    name: name.join(" "),
+                    projectile,
+                    speed: amount(speed)?,
+                    spread: amount(spread)?,
+                    count,
+                    cooldown: amount(cooldown)?,
+                    mana_cost: amount(mana_cost)?,
+                });
+            },
+            _ => return Err(error("expected spell <projectile> <speed> <spread> <count> <cooldown> <mana cost> <name>")),
+        }
+    }
+    if spells.is_empty() {
+        return Err("no spells".to_string())
+    }
+    Ok(spells)
+}
+
+// falls back to default_spells
+pub fn load_spells(assets: &mut Assets) -> Vec<SpellDef> {
+    assets.load_data(PATH)
+        .map_err(|err| format!("{:#}", err))
+        .and_then(|bytes| spells_from_config_str(&String::from_utf8_lossy(&bytes)))
+        .unwrap_or_else(|err| {
+            log::warn!("Couldn't load {}: {}", PATH, err);
+            default_spells()
+        })
+}
+
+// the spells the player starts with when spells.cfg can't be loaded, in number key order
+pub fn default_spells() -> Vec<SpellDef> {
+    vec![
+        SpellDef {
+            name: "Holy Bolt".to_string(),
+            projectile: ProjectileType::Basic,
+            speed: 10.0,
+            spread: 0.0,
+            count: 1,
+            cooldown: 0.25,
+            mana_cost: 5.0,
+        },
+        SpellDef {
+            name: "Curse".to_string(),
+            projectile: ProjectileType::Slowing,
+            speed: 8.0,
+            spread: 0.0,
+            count: 1,
+            cooldown: 0.6,
+            mana_cost: 15.0,
+        },
+        SpellDef {
+            name: "Scatter".to_string(),
+            projectile: ProjectileType::Bouncing,
+            speed: 9.0,
+            spread: 0.6,
+            count: 3,
+            cooldown: 0.9,
+            mana_cost: 25.0,
+        },
+        SpellDef {
+            name: "Seeker".to_string(),
+            projectile: ProjectileType::Seeking,
+            speed: 6.0,
+            spread: 0.8,
+            count: 2,
+            cooldown: 1.2,
+            mana_cost: 30.0,
+        },
+        SpellDef {
+            name: "Lance".to_string(),
+            projectile: ProjectileType::Piercing,
+            speed: 16.0,
+            spread: 0.0,
+            count: 1,
+            cooldown: 0.7,
+            mana_cost: 20.0,
+        },
+        SpellDef {
+            name: "Holy Bomb".to_string(),
+            projectile: ProjectileType::Bomb,
+            speed: 8.0,
+            spread: 0.0,
+            count: 1,
+            cooldown: 2.0,
+            mana_cost: 40.0,
+        },
+    ]
+}
+
+pub struct Spellbook {
+    pub spells: Vec<SpellDef>,
+    pub current: usize,
+    // remaining cooldown per spell, in the same order as spells
+    cooldowns: Vec<f32>,
+    pub mana: f32,
+    pub max_mana: f32,
+}
+
+impl Spellbook {
+    const MAX_MANA: f32 = 100.0;
+    // mana per second
+    const MANA_REGEN: f32 = 15.0;
+
+    pub fn new(spells: Vec<SpellDef>) -> Self {
+        assert!(!spells.is_empty());
+        Self {
+            cooldowns: vec![0.0; spells.len()],
+            spells,
+            current: 0,
+            mana: Self::MAX_MANA,
+            max_mana: Self::MAX_MANA,
+        }
+    }
+
+    // swaps in changed spells, keeping the mana, and the selection and cooldowns of the spells still there
+    pub fn set_spells(&mut self, spells: Vec<SpellDef>) {
+        assert!(!spells.is_empty());
+        self.cooldowns.resize(spells.len(), 0.0);
+        self.current = self.current.min(spells.len() - 1);
+        self.spells = spells;
+    }
+
+    pub fn current_spell(&self) -> &SpellDef {
+        &self.spells[self.current]
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.spells.len() {
+            self.current = index;
+        }
+    }
+
+    // moves the selection by offset, wrapping around at either end
+    pub fn cycle(&mut self, offset: i32) {
+        let len = self.spells.len() as i32;
+        self.current = (self.current as i32 + offset).rem_euclid(len) as usize;
+    }
+
+    pub fn cooldown_remaining(&self) -> f32 {
+        self.cooldowns[self.current]
+    }
+
+    // 1.0 right after casting, 0.0 when ready
+    pub fn cooldown_fraction(&self) -> f32 {
+        let cooldown = self.current_spell().cooldown;
+        if cooldown <= 0.0 {
+            0.0
+        } else {
+            f32::clamp(self.cooldown_remaining() / cooldown, 0.0, 1.0)
+        }
+    }
+
+    pub fn can_cast(&self) -> bool {
+        self.cooldown_remaining() <= 0.0 && self.mana >= self.current_spell().mana_cost
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.mana = f32::min(self.max_mana, self.mana + Self::MANA_REGEN * delta_time);
+        self.cooldowns.iter_mut().for_each(|cooldown| *cooldown = f32::max(0.0, *cooldown - delta_time));
+    }
+
+    // pays for the current spell and starts its cooldown, returning it if it was castable
+    pub fn try_cast(&mut self) -> Option<SpellDef> {
+        if !self.can_cast() {
+            return None
+        }
+        let spell = self.current_spell().clone();
+        self.mana -= spell.mana_cost;
+        self.cooldowns[self.current] = spell.cooldown;
+        Some(spell)
+    }
+}
+
+pub fn aim_direction(from: Vector2<f32>, to: Vector2<f32>) -> Option<Vector2<f32>> {
+    let dir = to - from;
+    if dir.magnitude2() != 0.0 {
+        Some(dir.normalize())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_spells_match_the_fallback() {
+        let config = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/spells.cfg")).unwrap();
+        assert_eq!(spells_from_config_str(&config).unwrap(), default_spells());
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        let error = |config: &str| spells_from_config_str(config).err().unwrap();
+        assert_eq!(error("# nothing\n\n"), "no spells");
+        assert_eq!(error("spell fire 10 0 1 0.25 5 Fireball"), "line 1: fire is not one of basic, slowing, bouncing, seeking, piercing, bomb");
+        assert_eq!(error("spell basic 10 0 0 0.25 5 Holy Bolt"), "line 1: 0 is not a positive count");
+        assert_eq!(error("\nspell basic -1 0 1 0.25 5 Holy Bolt"), "line 2: -1 is not a number of at least 0");
+        assert_eq!(error("spell basic 10 0 1 inf 5 Holy Bolt"), "line 1: inf is not a number of at least 0");
+        assert!(error("spell basic 10 0 1 0.25 5").starts_with("line 1: expected spell"));
+    }
+
+    #[test]
+    fn changed_spells_keep_what_still_applies() {
+        let mut spellbook = Spellbook::new(default_spells());
+        spellbook.select(5);
+        assert!(spellbook.try_cast().is_some());
+        spellbook.set_spells(default_spells());
+        assert_eq!(spellbook.current, 5);
+        assert!(spellbook.cooldown_remaining() > 0.0);
+        assert_eq!(spellbook.mana, Spellbook::MAX_MANA - 40.0);
+        // the selected spell is gone, so the last one left is selected
+        spellbook.set_spells(default_spells()[..2].to_vec());
+        assert_eq!(spellbook.current, 1);
+        assert_eq!(spellbook.cooldown_remaining(), 0.0);
+        spellbook.cycle(1);
+        assert_eq!(spellbook.current, 0);
+    }
+}
@@ -1,6 +1,6 @@
 use cgmath::{Vector2, Zero};
 use uuid::Uuid;
-use crate::bounding_box::BoundingBox;
+use crate::{assets::Assets, bounding_box::BoundingBox};
 use super::{physics::{PhysicsObject, Physics, PhysObjType}, GameObject, IDObject};
 use std::collections::HashMap;
 
@@ -9,9 +9,76 @@ pub enum TileType {
     Dirt
 }
 
+// a sprite placed in the level that nothing collides with
+pub struct Decoration {
+    pub position: Vector2<f32>,
+    pub size: Vector2<f32>,
+    pub sprite: &'static str,
+    // drawn over the characters instead of behind the tiles
+    pub foreground: bool,
+}
+
+// the tiles of a stage, read from level.txt so they can be changed while the game runs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StageLayout {
+    pub tiles: Vec<Vector2<i32>>,
+}
+
+impl StageLayout {
+    pub const PATH: &'static str = "level.txt";
+
+    // "origin <x> <y>" is the tile the first character of the next row goes on, each row after it is one tile lower.
+    // in rows x is dirt and . is empty. blank lines and lines starting with # are skipped
+    pub fn from_config_str(config: &str) -> Result<StageLayout, String> {
+        let mut tiles = vec![];
+        let mut row_start = None;
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            if let Some(origin) = line.strip_prefix("origin") {
+                let origin = origin.split_whitespace()
+                    .map(|n| n.parse::<i32>().map_err(|_| error(&format!("{} is not a whole number", n))))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match origin[..] {
+                    [x, y] => row_start = Some(Vector2::new(x, y)),
+                    _ => return Err(error("expected origin <x> <y>")),
+                }
+                continue
+            }
+            let start = row_start.ok_or_else(|| error("expected an origin before the first row"))?;
+            for (i, c) in line.chars().enumerate() {
+                match c {
+                    'x' => tiles.push(start + Vector2::new(i as i32, 0)),
+                    '.' => (),
+                    c => return Err(error(&format!("{} is not a tile, expected x or .", c))),
+                }
+            }
+            row_start = Some(start + Vector2::new(0, 1));
+        }
+        Ok(StageLayout { tiles })
+    }
+
+    // falls back to no tiles
+    pub fn load(assets: &mut Assets) -> StageLayout {
+        assets.load_data(Self::PATH)
+            .map_err(|err| format!("{:#}", err))
+            .and_then(|bytes| StageLayout::from_config_str(&String::from_utf8_lossy(&bytes)))
+            .unwrap_or_else(|err| {
+                log::warn!("Couldn't load {}: {}", Self::PATH, err);
+                StageLayout::default()
+            })
+    }
+}
+
 pub struct Stage {
     id: Uuid,
     pub tiles: HashMap<Vector2<i32>, (Uuid, TileType)>,
+    pub decorations: Vec<Decoration>,
+    // bumped whenever a tile in or next to the chunk changes, so whatever is cached per chunk knows to rebuild
+    chunk_revisions: HashMap<Vector2<i32>, u32>,
 }
 
 impl Stage {
@@ -20,20 +87,66 @@ impl Stage {
         Self {
             id: Uuid::new_v4(),
             tiles: HashMap::new(),
+            decorations: vec![],
+            chunk_revisions: HashMap::new(),
         }
     }
 
+    // width and height of a chunk in tiles
+    pub const CHUNK_SIZE: i32 = 16;
+
+    pub fn chunk_of(tile: Vector2<i32>) -> Vector2<i32> {
+        Vector2::new(tile.x.div_euclid(Self::CHUNK_SIZE), tile.y.div_euclid(Self::CHUNK_SIZE))
+    }
+
+    // every chunk that has ever had a tile
+    pub fn chunks(&self) -> impl Iterator<Item = (Vector2<i32>, u32)> + '_ {
+        self.chunk_revisions.iter().map(|(chunk, revision)| (*chunk, *revision))
+    }
+
     pub fn set_tile(&mut self, pos: &Vector2<i32>, value: Option<TileType>) {
         match value {
             None => self.tiles.remove(&pos),
             Some(typ) => self.tiles.insert(pos.clone(), (Uuid::new_v4(), typ)),
         };
-        println!("new tiles len: {}", self.tiles.len());
+        // neighbors look at this tile too, and they may be in the next chunk over
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let chunk = Self::chunk_of(pos + Vector2::new(dx, dy));
+                *self.chunk_revisions.entry(chunk).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // replaces every tile with the ones of layout
+    pub fn set_layout(&mut self, layout: &StageLayout) {
+        let old: Vec<Vector2<i32>> = self.tiles.keys().copied().collect();
+        for tile in old {
+            self.set_tile(&tile, None);
+        }
+        for tile in &layout.tiles {
+            self.set_tile(tile, Some(TileType::Dirt));
+        }
+        log::debug!("stage has {} tiles", self.tiles.len());
     }
 
     pub fn get_tile(&self, pos: &Vector2<i32>) -> Option<TileType> {
         self.tiles.get(pos).map(|(_, typ)| *typ)
     }
+
+    // the world space corners of the smallest box around every tile, none without tiles
+    pub fn bounds(&self) -> Option<(Vector2<f32>, Vector2<f32>)> {
+        self.tiles.keys().fold(None, |bounds, tile| {
+            let (min, max) = (Vector2::new(tile.x as f32, tile.y as f32), Vector2::new(tile.x as f32 + 1.0, tile.y as f32 + 1.0));
+            Some(match bounds {
+                None => (min, max),
+                Some((lo, hi)) => (
+                    Vector2::new(f32::min(lo.x, min.x), f32::min(lo.y, min.y)),
+                    Vector2::new(f32::max(hi.x, max.x), f32::max(hi.y, max.y)),
+                ),
+            })
+        })
+    }
 }
 
 impl GameObject for Stage {
@@ -70,3 +183,21 @@ impl Physics for Stage {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_go_down_from_the_origin() {
+        let layout = StageLayout::from_config_str("# comment\norigin -1 2\nx.x\n\n.x.\n").unwrap();
+        assert_eq!(layout.tiles, vec![Vector2::new(-1, 2), Vector2::new(1, 2), Vector2::new(0, 3)]);
+    }
+
+    #[test]
+    fn bad_layouts_say_which_line() {
+        assert_eq!(StageLayout::from_config_str("x.x"), Err("line 1: expected an origin before the first row".to_string()));
+        assert_eq!(StageLayout::from_config_str("origin 0 0\nx?"), Err("line 2: ? is not a tile, expected x or .".to_string()));
+        assert_eq!(StageLayout::from_config_str("origin 0"), Err("line 1: expected origin <x> <y>".to_string()));
+    }
+}